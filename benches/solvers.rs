@@ -0,0 +1,59 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::hint::black_box;
+use travelling_salesman::*;
+
+// Fixed instances, one `x y` pair per line, so timings compare across commits
+const SMALL: &str = include_str!("data/small_10.txt");
+const MEDIUM: &str = include_str!("data/medium_200.txt");
+
+fn parse_instance(data: &str) -> Vec<Point> {
+    read_points(data.as_bytes()).unwrap()
+}
+
+fn bench_brute_force(c: &mut Criterion) {
+    let grid = calculate_edge_grid(parse_instance(SMALL)).unwrap();
+
+    let mut group = c.benchmark_group("brute_force");
+    group.sample_size(10);
+    group.bench_function("n=10", |b| b.iter(|| brute_force(black_box(&grid)).unwrap()));
+    group.finish();
+}
+
+fn bench_heuristics(c: &mut Criterion) {
+    let grid = calculate_edge_grid(parse_instance(MEDIUM)).unwrap();
+
+    let mut group = c.benchmark_group("heuristics");
+    group.bench_function("nearest_neighbor_multistart n=200", |b| {
+        b.iter(|| nearest_neighbor_multistart(black_box(&grid), 0..grid.len()).unwrap())
+    });
+    group.bench_function("cheapest_insertion n=200", |b| b.iter(|| cheapest_insertion(black_box(&grid)).unwrap()));
+
+    let (start_tour, _) = nearest_neighbor(&grid, 0).unwrap();
+    group.bench_function("two_opt n=200", |b| b.iter(|| two_opt(black_box(start_tour.clone()), &grid).unwrap()));
+    group.bench_function("three_opt n=200", |b| b.iter(|| three_opt(black_box(start_tour.clone()), &grid).unwrap()));
+    group.finish();
+}
+
+fn bench_edge_grid(c: &mut Criterion) {
+    let medium = parse_instance(MEDIUM);
+    let large = generate_points_with(2000, 1000, false, &mut StdRng::seed_from_u64(1)).unwrap();
+    // the same work on one thread, to show what the parallel rows buy
+    let serial = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+
+    let mut group = c.benchmark_group("edge_grid");
+    for points in [&medium, &large] {
+        let n = points.len();
+        group.bench_function(format!("parallel n={n}"), |b| {
+            b.iter(|| calculate_edge_grid_with(black_box(points), Point::distance))
+        });
+        group.bench_function(format!("one thread n={n}"), |b| {
+            b.iter(|| serial.install(|| calculate_edge_grid_with(black_box(points), Point::distance)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_brute_force, bench_heuristics, bench_edge_grid);
+criterion_main!(benches);
@@ -0,0 +1,67 @@
+/// 1-tree lower bound on any tour: a minimum spanning tree of cities 1..n plus city 0's two
+/// cheapest edges, since a tour with one of its edges at 0 dropped is a spanning tree of the rest.
+/// Each edge is taken at its cheaper direction, so the bound holds on asymmetric grids too.
+pub fn one_tree_bound(grid: &[Vec<f32>]) -> f32 {
+    let n = grid.len();
+    let weight = |i: usize, j: usize| grid[i][j].min(grid[j][i]);
+    if n < 3 {
+        // two cities: there and back along the one edge
+        return if n == 2 { 2.0 * weight(0, 1) } else { 0.0 };
+    }
+
+    // Prim, from city 1
+    let mut in_tree = vec![false; n];
+    let mut nearest = vec![f32::INFINITY; n];
+    nearest[1] = 0.0;
+    let mut bound = 0.0;
+    for _ in 1..n {
+        let Some(next) = (1..n).filter(|&j| !in_tree[j]).min_by(|&a, &b| nearest[a].total_cmp(&nearest[b])) else {
+            break;
+        };
+        in_tree[next] = true;
+        bound += nearest[next];
+        for (j, distance) in nearest.iter_mut().enumerate().skip(1) {
+            if !in_tree[j] {
+                *distance = distance.min(weight(next, j));
+            }
+        }
+    }
+
+    let mut from_start: Vec<f32> = (1..n).map(|j| weight(0, j)).collect();
+    from_start.sort_by(f32::total_cmp);
+    bound + from_start[0] + from_start[1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::brute_force::brute_force;
+    use crate::grid::calculate_edge_grid;
+    use crate::point::{generate_points_with, Point};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn one_tree_bound_never_exceeds_the_optimum() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let n = rng.gen_range(2..=8);
+            let mut grid = calculate_edge_grid(generate_points_with(n, 100, false, &mut rng).unwrap()).unwrap();
+            let (_, optimum) = brute_force(&grid).unwrap();
+            assert!(one_tree_bound(&grid) <= optimum * (1.0 + 1e-5), "seed {seed}");
+
+            for distance in grid.iter_mut().flatten() {
+                *distance *= rng.gen_range(1.0..2.0);
+            }
+            let (_, optimum) = brute_force(&grid).unwrap();
+            assert!(one_tree_bound(&grid) <= optimum * (1.0 + 1e-5), "asymmetric seed {seed}");
+        }
+    }
+
+    #[test]
+    fn one_tree_bound_is_tight_on_a_square() {
+        let corners = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+        let grid = calculate_edge_grid(corners.iter().map(|&(x, y)| Point { x, y, label: None }).collect()).unwrap();
+        assert_eq!(one_tree_bound(&grid), 4.0);
+    }
+}
@@ -0,0 +1,1486 @@
+use core::f32;
+use log::{debug, trace};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use crate::checkpoint::Checkpoint;
+use crate::display::format_path;
+use crate::distance::Distance;
+use crate::error::TspError;
+use crate::grid::{calculate_edge_grid, is_symmetric, validate_grid};
+use crate::objective::{path_cost, time_dependent_cost, Objective};
+use crate::point::generate_points_with;
+use crate::tour::{bottleneck_edge, canonical_tour, rotate_to, tour_length, Edge};
+use crate::MAP_WIDTH;
+
+// (n-1)!/2
+/// Missing edges can be marked `f32::INFINITY`; tours using one are never chosen, and when every
+/// tour does the grid is `Infeasible`.
+pub fn brute_force(grid: &[Vec<f32>]) -> Result<(Vec<usize>, f32), TspError> {
+    validate_grid(grid)?;
+
+    let mut search = Search::new(grid)?;
+    search.run(vec![0]);
+    debug!("Evaluated {} complete tours", search.leaves);
+
+    if search.best.is_empty() {
+        return Err(TspError::Infeasible);
+    }
+    Ok((search.best, search.min))
+}
+
+/// Brute force that calls `on_progress` with the fraction of the search done, each time a branch
+/// on one of the first two cities after the start is finished, e.g. to draw a progress bar. The
+/// fraction is complete tours evaluated over the (n-1)! there are, halved on a symmetric grid
+/// where mirrors are skipped, so it reaches 1.0 at the end.
+pub fn brute_force_with_progress(grid: &[Vec<f32>], mut on_progress: impl FnMut(f64)) -> Result<(Vec<usize>, f32), TspError> {
+    validate_grid(grid)?;
+
+    let mut search = Search::new(grid)?;
+    search.progress = Some(&mut on_progress);
+    search.run(vec![0]);
+    debug!("Evaluated {} complete tours", search.leaves);
+
+    if search.best.is_empty() {
+        return Err(TspError::Infeasible);
+    }
+    Ok((search.best, search.min))
+}
+
+/// Brute force over the tours where, for every `(a, b)` in `precedences`, city `a` comes before
+/// city `b` (counting from the start city 0). Branches that would place `b` first are pruned as soon
+/// as they arise. Cyclic precedences, or ones no tour from city 0 can meet, are `Infeasible`.
+pub fn brute_force_with_precedences(grid: &[Vec<f32>], precedences: &[(usize, usize)]) -> Result<(Vec<usize>, f32), TspError> {
+    validate_grid(grid)?;
+    let n = grid.len();
+
+    let mut predecessors = vec![Vec::new(); n];
+    for &(a, b) in precedences {
+        if a >= n || b >= n {
+            return Err(TspError::InvalidTour(format!("precedence ({a}, {b}) refers to a city out of range")));
+        }
+        predecessors[b].push(a);
+    }
+    // nothing can come before the start city
+    if has_cycle(&predecessors) || !predecessors[0].is_empty() {
+        return Err(TspError::Infeasible);
+    }
+
+    let mut search = Search::new(grid)?;
+    if !precedences.is_empty() {
+        // a tour's mirror reverses the visiting order, so it may break a precedence the tour meets
+        search.symmetric = false;
+    }
+    search.predecessors = predecessors.iter().map(|cities| mask_of(cities)).collect();
+    search.run(vec![0]);
+    debug!("Evaluated {} complete tours", search.leaves);
+
+    if search.best.is_empty() {
+        return Err(TspError::Infeasible);
+    }
+    Ok((search.best, search.min))
+}
+
+/// Brute force over only the tours that use every edge in `required`, in either direction.
+/// Infeasible when no tour can contain them all, e.g. a city with three required edges.
+pub fn brute_force_with_required_edges(grid: &[Vec<f32>], required: &[Edge]) -> Result<(Vec<usize>, f32), TspError> {
+    validate_grid(grid)?;
+
+    let mut search = Search::new(grid)?;
+    search.required = required_partners(grid.len(), required)?;
+    search.run(vec![0]);
+    debug!("Evaluated {} complete tours", search.leaves);
+
+    if search.best.is_empty() {
+        return Err(TspError::Infeasible);
+    }
+    Ok((search.best, search.min))
+}
+
+/// Best tour among those where cities `i` and `j` are next to each other, in either order
+pub fn best_tour_with_adjacency(grid: &[Vec<f32>], i: usize, j: usize) -> Result<(Vec<usize>, f32), TspError> {
+    brute_force_with_required_edges(grid, &[(i, j)])
+}
+
+/// Brute force scored by `objective` instead of plain tour length, with optional per-city
+/// `service_times` (see `path_cost`)
+pub fn brute_force_with_objective(
+    grid: &[Vec<f32>],
+    objective: &Objective,
+    service_times: Option<&[f32]>,
+) -> Result<(Vec<usize>, f32), TspError> {
+    validate_grid(grid)?;
+    let n = grid.len();
+    if let Some(service_times) = service_times {
+        if service_times.len() != n {
+            return Err(TspError::SizeMismatch(n, service_times.len()));
+        }
+    }
+    if let Objective::TurnPenalty { points, .. } | Objective::DirectionChanges { points, .. } = objective {
+        if points.len() != n {
+            return Err(TspError::SizeMismatch(n, points.len()));
+        }
+    }
+
+    let cost = |tour: &[usize]| path_cost(tour, grid, objective, service_times);
+    let mut search = Search::new(grid)?;
+    search.symmetric &= objective.is_reversible();
+    search.cost = Some(&cost);
+    search.run(vec![0]);
+    debug!("Evaluated {} complete tours", search.leaves);
+
+    if search.best.is_empty() {
+        return Err(TspError::Infeasible);
+    }
+    Ok((search.best, search.min))
+}
+
+/// Keeps `fixed` as the start of the tour (e.g. stops already dispatched) and brute-forces the
+/// order of the remaining cities after it, closing back to `fixed[0]`. An empty `fixed` starts
+/// from city 0 as usual.
+pub fn optimize_with_fixed_prefix(grid: &[Vec<f32>], fixed: &[usize]) -> Result<(Vec<usize>, f32), TspError> {
+    validate_grid(grid)?;
+    let n = grid.len();
+    let prefix = if fixed.is_empty() { vec![0] } else { fixed.to_vec() };
+
+    let mut seen = vec![false; n];
+    for &city in &prefix {
+        if city >= n || seen[city] {
+            return Err(TspError::InvalidTour(format!("fixed prefix {fixed:?} is not a list of distinct cities")));
+        }
+        seen[city] = true;
+    }
+
+    let mut search = Search::new(grid)?;
+    // the mirror check compares against the second city, which is no longer free to choose
+    search.symmetric &= prefix.len() < 2;
+    search.run(prefix);
+    debug!("Evaluated {} complete tours", search.leaves);
+
+    if search.best.is_empty() {
+        return Err(TspError::Infeasible);
+    }
+    Ok((search.best, search.min))
+}
+
+/// Brute force that, among tours of equal length, prefers the one whose longest edge is shortest.
+/// Lengths that `Distance::ties` says are equal count as a tie, as float sums of the same edges in
+/// another order rarely match exactly.
+pub fn brute_force_with_edge_tiebreak(grid: &[Vec<f32>]) -> Result<(Vec<usize>, f32), TspError> {
+    validate_grid(grid)?;
+
+    let mut search = Search::new(grid)?;
+    search.longest_edge = Some(f32::INFINITY);
+    search.run(vec![0]);
+    debug!("Evaluated {} complete tours", search.leaves);
+
+    if search.best.is_empty() {
+        return Err(TspError::Infeasible);
+    }
+    Ok((search.best, search.min))
+}
+
+/// Brute force under time-dependent edge costs (see `time_dependent_cost`). Every rotation of a
+/// tour starting from city 0 is distinct here, and mirrors aren't skipped.
+pub fn brute_force_time_dependent(grid: &[Vec<f32>], time_factor: &dyn Fn(f32) -> f32) -> Result<(Vec<usize>, f32), TspError> {
+    validate_grid(grid)?;
+
+    let cost = |tour: &[usize]| time_dependent_cost(tour, grid, time_factor);
+    let mut search = Search::new(grid)?;
+    search.symmetric = false;
+    search.cost = Some(&cost);
+    search.run(vec![0]);
+    debug!("Evaluated {} complete tours", search.leaves);
+
+    if search.best.is_empty() {
+        return Err(TspError::Infeasible);
+    }
+    Ok((search.best, search.min))
+}
+
+/// Brute force that branches fully on only the first `max_depth` cities after the start. Below that
+/// each partial tour is finished greedily (nearest neighbor) instead of being enumerated.
+/// The flag is true when any branch was cut short, i.e. the result may not be optimal.
+/// As with every search here, grids over 64 cities are rejected.
+pub fn brute_force_to_depth(grid: &[Vec<f32>], max_depth: Option<usize>) -> Result<(Vec<usize>, f32, bool), TspError> {
+    validate_grid(grid)?;
+
+    let mut search = Search::new(grid)?;
+    search.max_depth = max_depth;
+    search.run(vec![0]);
+    debug!("Evaluated {} complete tours", search.leaves);
+
+    if search.best.is_empty() {
+        return Err(TspError::Infeasible);
+    }
+    Ok((search.best, search.min, search.truncated))
+}
+
+/// Brute force that saves a `Checkpoint` to `path` after every `every` complete tours and once more
+/// when done, so an interrupted run can be picked up with `resume_brute_force`
+pub fn brute_force_checkpointed(grid: &[Vec<f32>], path: &Path, every: u64) -> Result<(Vec<usize>, f32), TspError> {
+    validate_grid(grid)?;
+
+    let save = save_checkpoint(grid, path);
+    let mut search = Search::new(grid)?;
+    search.checkpoint = Some((&save, every));
+    search.run(vec![0]);
+    search.finish_checkpoint()?;
+    debug!("Evaluated {} complete tours", search.leaves);
+
+    if search.best.is_empty() {
+        return Err(TspError::Infeasible);
+    }
+    Ok((search.best, search.min))
+}
+
+/// Continues the search saved in `checkpoint`, skipping every tour up to its position, and keeps
+/// checkpointing to `path` as `brute_force_checkpointed` does
+pub fn resume_brute_force(checkpoint: Checkpoint, path: &Path, every: u64) -> Result<(Vec<usize>, f32), TspError> {
+    let grid = &checkpoint.grid;
+    validate_grid(grid)?;
+
+    let mut search = Search::new(grid)?;
+    search.min = checkpoint.min;
+    search.best = checkpoint.best.clone();
+    search.leaves = checkpoint.nodes;
+    if !checkpoint.position.is_empty() {
+        search.resume_after = Some(checkpoint.position.clone());
+    }
+    let save = save_checkpoint(grid, path);
+    search.checkpoint = Some((&save, every));
+    search.run(vec![0]);
+    search.finish_checkpoint()?;
+    debug!("Evaluated {} complete tours", search.leaves);
+
+    if search.best.is_empty() {
+        return Err(TspError::Infeasible);
+    }
+    Ok((search.best, search.min))
+}
+
+/// Brute force over any `Distance` scalar, e.g. the `u64` grid from `round_grid` where every sum
+/// is exact. Tours are visited in the same order as `brute_force`, and only a strictly shorter tour
+/// replaces the best, so ties always go to the first tour found.
+pub fn brute_force_exact<D: Distance>(grid: &[Vec<D>]) -> Result<(Vec<usize>, D), TspError> {
+    validate_grid(grid)?;
+
+    let mut search = Search::new(grid)?;
+    search.run(vec![0]);
+    debug!("Evaluated {} complete tours", search.leaves);
+
+    if search.best.is_empty() {
+        return Err(TspError::Infeasible);
+    }
+    Ok((search.best, search.min))
+}
+
+/// Saves the search over `grid` to `path` as a `Checkpoint`, given the best tour, its length, the
+/// last tour evaluated and how many have been
+fn save_checkpoint<'a>(
+    grid: &'a [Vec<f32>],
+    path: &'a Path,
+) -> impl Fn(&[usize], f32, &[usize], u64) -> Result<(), TspError> + 'a {
+    move |best, min, position, nodes| {
+        Checkpoint { grid: grid.to_vec(), best: best.to_vec(), min, position: position.to_vec(), nodes }.save(path)
+    }
+}
+
+/// Decision version of the TSP: the first tour found strictly shorter than `limit`, or `None`
+/// when there is none. Branches are cut as soon as their partial length
+/// reaches `limit` (when no edge is negative), and the search stops at the first hit instead of
+/// proving optimality, so a generous limit answers far sooner than `brute_force`.
+pub fn exists_tour_under(grid: &[Vec<f32>], limit: f32) -> Result<Option<Vec<usize>>, TspError> {
+    validate_grid(grid)?;
+
+    let mut search = BranchAndBound::new(grid, limit)?;
+    search.first_only = true;
+    search.run();
+
+    Ok(if search.best.is_empty() { None } else { Some(search.best) })
+}
+
+/// Bottleneck first, then distance: among the tours whose longest edge is as short as it can be,
+/// the shortest. Returns the tour, that longest edge and the tour's length. The bottleneck is
+/// found by binary search over the distinct edge lengths, asking `exists_tour_under` whether a
+/// tour avoiding every longer edge exists.
+pub fn bottleneck_then_distance(grid: &[Vec<f32>]) -> Result<(Vec<usize>, f32, f32), TspError> {
+    validate_grid(grid)?;
+    let n = grid.len();
+    if n == 1 {
+        return Ok((vec![0], 0.0, 0.0));
+    }
+
+    let mut weights: Vec<f32> = (0..n)
+        .flat_map(|i| (0..n).filter(move |&j| j != i).map(move |j| grid[i][j]))
+        .filter(|d| d.is_finite())
+        .collect();
+    weights.sort_by(f32::total_cmp);
+    weights.dedup();
+
+    // smallest threshold that still leaves a tour; feasibility only grows with the threshold
+    let (mut low, mut high) = (0, weights.len());
+    while low < high {
+        let mid = (low + high) / 2;
+        if exists_tour_under(&without_edges_over(grid, weights[mid]), f32::INFINITY)?.is_some() {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    let Some(&bottleneck) = weights.get(low) else {
+        return Err(TspError::Infeasible);
+    };
+
+    let (tour, _) = branch_and_bound_anytime(&without_edges_over(grid, bottleneck), |_, _| {})?;
+    let length = tour_length(grid, &tour)?;
+    Ok((tour, bottleneck, length))
+}
+
+/// As `branch_and_bound_anytime` without the callback, also returning how many search nodes
+/// (partial tours) were expanded: a measure of how hard the instance is to prove
+pub fn branch_and_bound_counted(grid: &[Vec<f32>]) -> Result<(Vec<usize>, f32, u64), TspError> {
+    validate_grid(grid)?;
+
+    let mut search = BranchAndBound::new(grid, f32::INFINITY)?;
+    search.run();
+
+    if search.best.is_empty() {
+        return Err(TspError::Infeasible);
+    }
+    Ok((search.best, search.limit, search.nodes))
+}
+
+/// Branch and bound over only the tours that use every edge in `required`, in either direction, as
+/// `brute_force_with_required_edges` does. Infeasible when no tour can contain them all.
+pub fn branch_and_bound_with_required_edges(grid: &[Vec<f32>], required: &[Edge]) -> Result<(Vec<usize>, f32), TspError> {
+    validate_grid(grid)?;
+
+    let mut search = BranchAndBound::new(grid, f32::INFINITY)?;
+    search.required = required_partners(grid.len(), required)?;
+    search.run();
+    debug!("Evaluated {} complete tours", search.leaves);
+
+    if search.best.is_empty() {
+        return Err(TspError::Infeasible);
+    }
+    Ok((search.best, search.limit))
+}
+
+/// Copy of `grid` with every edge longer than `threshold` marked missing
+fn without_edges_over(grid: &[Vec<f32>], threshold: f32) -> Vec<Vec<f32>> {
+    grid.iter().map(|row| row.iter().map(|&d| if d > threshold { f32::INFINITY } else { d }).collect()).collect()
+}
+
+/// Branch and bound that calls `on_incumbent` with each new best tour as soon as it is found, e.g.
+/// so a UI can show improving tours long before optimality is proven. Lengths strictly decrease
+/// from call to call, and the last call is the optimum, which is also returned.
+pub fn branch_and_bound_anytime(
+    grid: &[Vec<f32>],
+    mut on_incumbent: impl FnMut(&[usize], f32),
+) -> Result<(Vec<usize>, f32), TspError> {
+    validate_grid(grid)?;
+
+    let mut search = BranchAndBound::new(grid, f32::INFINITY)?;
+    search.on_incumbent = Some(&mut on_incumbent);
+    search.run();
+    debug!("Evaluated {} complete tours", search.leaves);
+
+    if search.best.is_empty() {
+        return Err(TspError::Infeasible);
+    }
+    Ok((search.best, search.limit))
+}
+
+/// Branch and bound warm-started from a known tour, e.g. the previous answer before a small
+/// change: its length is the bound from the start, so only strictly shorter tours are explored
+/// and a tour that is already optimal is proven so with few nodes. Returns the warm tour, rotated
+/// to start at city 0, when nothing beats it.
+pub fn branch_and_bound_warm(grid: &[Vec<f32>], warm_tour: &[usize]) -> Result<(Vec<usize>, f32), TspError> {
+    let length = tour_length(grid, warm_tour)?;
+
+    let mut search = BranchAndBound::new(grid, length)?;
+    search.best = rotate_to(warm_tour, 0);
+    search.run();
+    debug!("Evaluated {} complete tours", search.leaves);
+
+    if !search.limit.is_finite() {
+        return Err(TspError::Infeasible);
+    }
+    Ok((search.best, search.limit))
+}
+
+/// The `k` shortest tours, shortest first, each starting at city 0. Tours are kept by their
+/// `canonical_tour`, so a tour and its reversal never both appear: on a symmetric grid the
+/// canonical direction is listed, and on an asymmetric one the shorter direction. Fewer than `k` come back when
+/// the instance has fewer distinct finite tours.
+pub fn k_best_tours(grid: &[Vec<f32>], k: usize) -> Result<Vec<(Vec<usize>, f32)>, TspError> {
+    validate_grid(grid)?;
+    let n = grid.len();
+    if n > MAX_CITIES {
+        return Err(TspError::TooManyCities(MAX_CITIES, n));
+    }
+
+    if k == 0 {
+        return Ok(Vec::new());
+    }
+
+    let all = if n == MAX_CITIES { u64::MAX } else { (1 << n) - 1 };
+    let mut search = KBest { grid, symmetric: is_symmetric(grid), k, all, kept: BinaryHeap::new(), canonical: HashSet::new() };
+    search.traverse(&mut vec![0], 1, 0.0);
+    if search.kept.is_empty() {
+        return Err(TspError::Infeasible);
+    }
+
+    Ok(search.kept.into_sorted_vec().into_iter().map(|ranked| (ranked.tour, ranked.length)).collect())
+}
+
+/// Callback receiving the fraction of a search done so far
+type OnProgress<'a> = &'a mut dyn FnMut(f64);
+
+/// Callback receiving each new best tour and its length
+type OnIncumbent<'a> = &'a mut dyn FnMut(&[usize], f32);
+
+/// Depth-first search that cuts a branch once its partial length reaches `limit`, the length of
+/// the best tour so far (or a caller's bound). Unlike `Search` this needs additive edge costs, and
+/// it branches to the nearest pending city first rather than in lexicographic order.
+struct BranchAndBound<'a> {
+    grid: &'a [Vec<f32>],
+    symmetric: bool,
+    /// Partial lengths only grow when no edge is negative, so reaching `limit` ends a branch
+    prune: bool,
+    all: u64,
+    /// Only tours strictly shorter than this are kept; each one found lowers it
+    limit: f32,
+    /// Stop at the first tour under `limit` rather than proving the optimum
+    first_only: bool,
+    /// Cities each city must be next to in the tour, as in `Search`
+    required: Vec<Vec<usize>>,
+    on_incumbent: Option<OnIncumbent<'a>>,
+    best: Vec<usize>,
+    leaves: u64,
+    /// Calls to `extend`, pruned or not
+    nodes: u64,
+}
+
+impl<'a> BranchAndBound<'a> {
+    fn new(grid: &'a [Vec<f32>], limit: f32) -> Result<Self, TspError> {
+        let n = grid.len();
+        if n > MAX_CITIES {
+            return Err(TspError::TooManyCities(MAX_CITIES, n));
+        }
+
+        Ok(BranchAndBound {
+            grid,
+            symmetric: is_symmetric(grid),
+            prune: grid.iter().flatten().all(|d| *d >= 0.0),
+            all: if n == MAX_CITIES { u64::MAX } else { (1 << n) - 1 },
+            limit,
+            first_only: false,
+            required: vec![Vec::new(); n],
+            on_incumbent: None,
+            best: Vec::new(),
+            leaves: 0,
+            nodes: 0,
+        })
+    }
+
+    fn run(&mut self) {
+        self.extend(&mut vec![0], 1, 0.0);
+    }
+
+    /// Explores the completions of `path`, of length `cost` so far; true once the search is done
+    fn extend(&mut self, path: &mut Vec<usize>, visited: u64, cost: f32) -> bool {
+        self.nodes += 1;
+        if self.prune && cost >= self.limit {
+            return false;
+        }
+        let last = path[path.len()-1];
+        let pending = self.all & !visited;
+        if pending == 0 {
+            if !closes_with_required(&self.required, path) {
+                return false;
+            }
+            self.leaves += 1;
+            let total = cost + self.grid[last][path[0]];
+            if total < self.limit {
+                self.limit = total;
+                self.best.clear();
+                self.best.extend_from_slice(path);
+                debug!("{}", format_path(path));
+                debug!("\t\t\tNew min: {total}");
+                if let Some(on_incumbent) = self.on_incumbent.as_mut() {
+                    on_incumbent(path, total);
+                }
+                return self.first_only;
+            }
+            return false;
+        }
+        // mirror of a tour already covered: no city left can be the last one
+        if self.symmetric && path.len() >= 2 && pending >> path[1] == 0 {
+            return false;
+        }
+
+        // nearest first: good tours turn up early, so `limit` drops fast and cuts more branches
+        let mut candidates = cities_of(pending);
+        candidates.sort_by(|&a, &b| self.grid[last][a].total_cmp(&self.grid[last][b]));
+        for city in candidates {
+            if !meets_required(&self.required, path, city) {
+                continue;
+            }
+            path.push(city);
+            let done = self.extend(path, visited | 1 << city, cost + self.grid[last][city]);
+            path.pop();
+            if done {
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+/// Cities in the instance timed to calibrate `estimate_brute_force_time`
+const CALIBRATION_CITIES: usize = 8;
+
+/// Number of complete tours brute force evaluates on a symmetric n-city grid: (n-1)!/2
+fn leaf_count(n: usize) -> f64 {
+    if n > 2 { tour_count(n) / 2.0 } else { 1.0 }
+}
+
+/// Closed tours of n cities from a fixed start, counting mirrors apart: (n-1)!
+fn tour_count(n: usize) -> f64 {
+    (2..n).map(|k| k as f64).product()
+}
+
+/// Time spent per complete tour, measured once on a fixed small instance and reused afterwards
+fn leaf_cost() -> Duration {
+    static LEAF_COST: OnceLock<Duration> = OnceLock::new();
+    *LEAF_COST.get_or_init(|| {
+        let mut rng = StdRng::seed_from_u64(0);
+        let points = generate_points_with(CALIBRATION_CITIES, MAP_WIDTH, false, &mut rng).unwrap();
+        let grid = calculate_edge_grid(points).unwrap();
+
+        let start = Instant::now();
+        let _ = brute_force(&grid);
+        start.elapsed().div_f64(leaf_count(CALIBRATION_CITIES))
+    })
+}
+
+/// Rough wall-clock time `brute_force` will need for n cities: the calibrated per-tour cost times
+/// the number of tours it enumerates
+pub fn estimate_brute_force_time(n: usize) -> Duration {
+    leaf_cost().mul_f64(leaf_count(n).min(1e18))
+}
+
+/// Visited cities are kept as bits of a `u64`
+const MAX_CITIES: usize = 64;
+
+/// Scores a complete tour in place of its plain length
+type TourCost<'a, D> = &'a dyn Fn(&[usize]) -> D;
+
+/// Saves the best tour, its length, the last tour evaluated and the number evaluated so far
+type OnCheckpoint<'a, D> = &'a dyn Fn(&[usize], D, &[usize], u64) -> Result<(), TspError>;
+
+/// State shared across the whole recursion: the instance and the best tour found so far
+struct Search<'a, D: Distance = f32> {
+    grid: &'a [Vec<D>],
+    /// On a symmetric grid a tour and its mirror cost the same, so only the one whose second city
+    /// is smaller than its last city is explored
+    symmetric: bool,
+    /// Scoring other than plain tour length, e.g. an `Objective` or time-dependent edge costs
+    cost: Option<TourCost<'a, D>>,
+    /// Bitmask of every city in the grid
+    all: u64,
+    /// predecessors[b]: bitmask of the cities that must already be visited before b
+    predecessors: Vec<u64>,
+    /// Number of decisions after the start city to branch on before completing greedily
+    max_depth: Option<usize>,
+    truncated: bool,
+    /// Cities each city must be next to in the tour, from `brute_force_with_required_edges`
+    required: Vec<Vec<usize>>,
+    /// With the longest-edge tie-break, the longest edge of the best tour so far
+    longest_edge: Option<D>,
+    /// Where to save progress, and how many complete tours between saves
+    checkpoint: Option<(OnCheckpoint<'a, D>, u64)>,
+    /// Last tour evaluated before a resume: it and everything before it are skipped
+    resume_after: Option<Vec<usize>>,
+    last_leaf: Vec<usize>,
+    checkpoint_error: Option<TspError>,
+    /// Told the fraction of tours evaluated as each of the top two levels of branches finishes
+    progress: Option<OnProgress<'a>>,
+    min: D,
+    best: Vec<usize>,
+    leaves: u64,
+}
+
+impl<'a, D: Distance> Search<'a, D> {
+    fn new(grid: &'a [Vec<D>]) -> Result<Self, TspError> {
+        let n = grid.len();
+        if n > MAX_CITIES {
+            return Err(TspError::TooManyCities(MAX_CITIES, n));
+        }
+
+        Ok(Search {
+            grid,
+            symmetric: is_symmetric(grid),
+            all: if n == MAX_CITIES { u64::MAX } else { (1 << n) - 1 },
+            cost: None,
+            predecessors: vec![0; n],
+            max_depth: None,
+            truncated: false,
+            required: vec![Vec::new(); grid.len()],
+            longest_edge: None,
+            checkpoint: None,
+            resume_after: None,
+            last_leaf: Vec::new(),
+            checkpoint_error: None,
+            progress: None,
+            min: D::MAX,
+            best: Vec::new(),
+            leaves: 0,
+        })
+    }
+
+    /// Explores every completion of `prefix`
+    fn run(&mut self, mut prefix: Vec<usize>) {
+        let visited = mask_of(&prefix);
+        self.traverse(&mut prefix, visited);
+    }
+
+    /// Extends `path` one city at a time, in ascending order so tours come out lexicographically.
+    /// `visited` holds a bit per city on the path, which keeps the search to one path buffer.
+    fn traverse(&mut self, path: &mut Vec<usize>, visited: u64) {
+        if self.checkpoint_error.is_some() {
+            return;
+        }
+        let pending = self.all & !visited;
+        if let Some(position) = &self.resume_after {
+            // the whole subtree comes before the resume point
+            if path.as_slice() < &position[..path.len().min(position.len())] {
+                return;
+            }
+            if pending == 0 {
+                if *path <= *position {
+                    return;
+                }
+                self.resume_after = None;
+            }
+        }
+
+        if pending != 0 {
+            // mirror of a tour already covered: no city left can be the last one
+            if self.symmetric && path.len() >= 2 && pending >> path[1] == 0 {
+                return;
+            }
+            if self.max_depth.is_some_and(|d| path.len() > d) {
+                // a single pending city leaves nothing to choose
+                self.truncated |= pending.count_ones() > 1;
+                self.evaluate(&complete_greedily(path.clone(), cities_of(pending), self.grid));
+                return;
+            }
+
+            // permutation
+            let mut remaining = pending;
+            while remaining != 0 {
+                let city = remaining.trailing_zeros() as usize;
+                remaining &= remaining - 1;
+                if self.predecessors[city] & !visited != 0 {
+                    continue;
+                }
+                if !meets_required(&self.required, path, city) {
+                    continue;
+                }
+                path.push(city);
+                self.traverse(path, visited | 1 << city);
+                path.pop();
+            }
+            // a first- or second-level branch after the start is done
+            if (2..=3).contains(&path.len()) {
+                if let Some(on_progress) = self.progress.as_mut() {
+                    let n = self.grid.len();
+                    let tours = if self.symmetric { leaf_count(n) } else { tour_count(n) };
+                    on_progress((self.leaves as f64 / tours).min(1.0));
+                }
+            }
+        } else {
+            // closing edge, seen from the last city and then from the start
+            if !closes_with_required(&self.required, path) {
+                return;
+            }
+            self.evaluate(path);
+        }
+    }
+
+    fn evaluate(&mut self, visited: &[usize]) {
+        let grid = self.grid;
+        let total_distance = match self.cost {
+            Some(cost) => cost(visited),
+            None => {
+                let n = visited.len();
+                let first_ix = visited[0];
+                let last_ix = visited[n-1];
+                let mut total_distance = grid[last_ix][first_ix];
+                for i in 0..n-1 {
+                    let a_city = visited[i];
+                    let b_city = visited[i+1];
+                    total_distance = total_distance + grid[a_city][b_city];
+                }
+                total_distance
+            }
+        };
+        self.leaves += 1;
+
+        trace!("{}", format_path(visited));
+        let improves = match self.longest_edge {
+            Some(best_longest) => {
+                let (_, _, longest) = bottleneck_edge(visited, grid);
+                let improves = if total_distance.ties(self.min) {
+                    longest < best_longest
+                } else {
+                    total_distance < self.min
+                };
+                if improves {
+                    self.longest_edge = Some(longest);
+                }
+                improves
+            }
+            None => total_distance < self.min,
+        };
+        if improves {
+            self.min = total_distance;
+
+            debug!("{}", format_path(visited));
+            debug!("\t\t\tNew min: {}", self.min);
+            self.best.clear();
+            self.best.extend_from_slice(visited);
+        }
+
+        if let Some((save, every)) = self.checkpoint {
+            if every > 0 && self.leaves.is_multiple_of(every) {
+                if let Err(e) = save(&self.best, self.min, visited, self.leaves) {
+                    self.checkpoint_error = Some(e);
+                }
+            }
+        }
+        self.last_leaf.clear();
+        self.last_leaf.extend_from_slice(visited);
+    }
+
+    /// Surfaces any failed save, then records the finished search
+    fn finish_checkpoint(&mut self) -> Result<(), TspError> {
+        if let Some(e) = self.checkpoint_error.take() {
+            return Err(e);
+        }
+        match (&self.checkpoint, &self.resume_after) {
+            (Some((save, _)), None) if !self.last_leaf.is_empty() => {
+                save(&self.best, self.min, &self.last_leaf, self.leaves)
+            }
+            // nothing new was evaluated, e.g. resuming a finished search: keep the saved state
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Tour in the `k_best_tours` heap, ordered by length so the longest kept is on top
+struct Ranked {
+    length: f32,
+    tour: Vec<usize>,
+    canonical: Vec<usize>,
+}
+
+impl PartialEq for Ranked {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Ranked {}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.length.total_cmp(&other.length).then_with(|| self.tour.cmp(&other.tour))
+    }
+}
+
+/// Exhaustive search keeping the `k` shortest tours seen, at most one per canonical form
+struct KBest<'a> {
+    grid: &'a [Vec<f32>],
+    /// Reversals tie here, so only the canonical direction is kept, whatever the rounding says
+    symmetric: bool,
+    k: usize,
+    all: u64,
+    kept: BinaryHeap<Ranked>,
+    /// Canonical forms of the tours in `kept`
+    canonical: HashSet<Vec<usize>>,
+}
+
+impl KBest<'_> {
+    fn traverse(&mut self, path: &mut Vec<usize>, visited: u64, cost: f32) {
+        let last = path[path.len() - 1];
+        if visited == self.all {
+            self.offer(path, cost + self.grid[last][0]);
+            return;
+        }
+
+        for next in cities_of(self.all & !visited) {
+            path.push(next);
+            self.traverse(path, visited | 1 << next, cost + self.grid[last][next]);
+            path.pop();
+        }
+    }
+
+    fn offer(&mut self, tour: &[usize], length: f32) {
+        if !length.is_finite() || self.kept.len() == self.k && self.kept.peek().is_some_and(|worst| length >= worst.length) {
+            return;
+        }
+
+        let canonical = canonical_tour(tour);
+        if self.symmetric && canonical != tour {
+            return;
+        }
+        if self.canonical.contains(&canonical) {
+            // the reversal of a kept tour: only worth swapping in when it is strictly shorter
+            if self.kept.iter().any(|ranked| ranked.canonical == canonical && ranked.length <= length) {
+                return;
+            }
+            self.kept.retain(|ranked| ranked.canonical != canonical);
+        }
+        self.canonical.insert(canonical.clone());
+        self.kept.push(Ranked { length, tour: tour.to_vec(), canonical });
+
+        if self.kept.len() > self.k {
+            if let Some(dropped) = self.kept.pop() {
+                self.canonical.remove(&dropped.canonical);
+            }
+        }
+    }
+}
+
+/// Cities each city must be next to for a tour to use every edge in `required`
+fn required_partners(n: usize, required: &[Edge]) -> Result<Vec<Vec<usize>>, TspError> {
+    let mut partners = vec![Vec::new(); n];
+    for &(a, b) in required {
+        if a >= n || b >= n || a == b {
+            return Err(TspError::InvalidTour(format!("required edge ({a}, {b}) is not between two cities")));
+        }
+        if !partners[a].contains(&b) {
+            partners[a].push(b);
+            partners[b].push(a);
+        }
+    }
+
+    Ok(partners)
+}
+
+/// Whether `next` can follow the path so far: the last city's `required` partners must be the
+/// city before it or `next`, as it gets no other neighbour. The start's other neighbour is only
+/// known once the tour closes.
+fn meets_required(required: &[Vec<usize>], visited: &[usize], next: usize) -> bool {
+    if visited.len() < 2 {
+        return true;
+    }
+    let (before, last) = (visited[visited.len()-2], visited[visited.len()-1]);
+    required[last].iter().all(|&p| p == next || p == before)
+}
+
+/// Whether the closing edge back to the start keeps `path` to its `required` edges, seen from the
+/// last city and then from the start
+fn closes_with_required(required: &[Vec<usize>], path: &[usize]) -> bool {
+    let (first, last) = (path[0], path[path.len()-1]);
+    meets_required(required, path, first) && meets_required(required, &[last, first], path[1 % path.len()])
+}
+
+fn mask_of(cities: &[usize]) -> u64 {
+    cities.iter().fold(0, |mask, city| mask | 1 << city)
+}
+
+/// Cities of a bitmask, in ascending order
+fn cities_of(mut mask: u64) -> Vec<usize> {
+    let mut cities = Vec::with_capacity(mask.count_ones() as usize);
+    while mask != 0 {
+        cities.push(mask.trailing_zeros() as usize);
+        mask &= mask - 1;
+    }
+
+    cities
+}
+
+/// Appends the pending cities in nearest-neighbor order from the end of `visited`
+fn complete_greedily<D: Distance>(mut visited: Vec<usize>, mut pending: Vec<usize>, grid: &[Vec<D>]) -> Vec<usize> {
+    while !pending.is_empty() {
+        let current = visited[visited.len()-1];
+        let mut k = 0;
+        for i in 1..pending.len() {
+            if grid[current][pending[i]] < grid[current][pending[k]] {
+                k = i;
+            }
+        }
+        visited.push(pending.swap_remove(k));
+    }
+
+    visited
+}
+
+/// Depth-first search for a cycle in the precedence graph
+fn has_cycle(predecessors: &[Vec<usize>]) -> bool {
+    // 0 = unseen, 1 = on the current path, 2 = done
+    fn visit(city: usize, predecessors: &[Vec<usize>], state: &mut Vec<u8>) -> bool {
+        state[city] = 1;
+        for &p in &predecessors[city] {
+            if state[p] == 1 || (state[p] == 0 && visit(p, predecessors, state)) {
+                return true;
+            }
+        }
+        state[city] = 2;
+        false
+    }
+
+    let mut state = vec![0; predecessors.len()];
+    (0..predecessors.len()).any(|city| state[city] == 0 && visit(city, predecessors, &mut state))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::distance::round_grid;
+    use crate::geometry::{tour_axis_changes, tour_turn_angles};
+    use crate::grid::calculate_edge_grid;
+    use crate::held_karp::held_karp;
+    use crate::metric::{calculate_edge_grid_metric, Metric};
+    use crate::objective::ReturnPolicy;
+    use crate::point::{generate_points_with, Point};
+    use crate::solve::solve_auto;
+    use crate::tour::{canonical_tour, tour_edges, tour_length, validate_tour};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn seeded(n: usize, seed: u64) -> Vec<Vec<f32>> {
+        calculate_edge_grid(generate_points_with(n, 100, false, &mut StdRng::seed_from_u64(seed)).unwrap()).unwrap()
+    }
+
+    fn grid_of(coordinates: &[(f64, f64)]) -> Vec<Vec<f32>> {
+        calculate_edge_grid(coordinates.iter().map(|&(x, y)| Point { x, y, label: None }).collect()).unwrap()
+    }
+
+    /// Every tour starting at city 0, mirrors included
+    fn all_tours(n: usize) -> Vec<Vec<usize>> {
+        fn extend(path: Vec<usize>, n: usize, out: &mut Vec<Vec<usize>>) {
+            if path.len() == n {
+                out.push(path);
+                return;
+            }
+            for city in 1..n {
+                if !path.contains(&city) {
+                    let mut next = path.clone();
+                    next.push(city);
+                    extend(next, n, out);
+                }
+            }
+        }
+        let mut out = Vec::new();
+        extend(vec![0], n, &mut out);
+        out
+    }
+
+    fn unpruned_min(grid: &[Vec<f32>], objective: &Objective) -> f32 {
+        all_tours(grid.len()).iter().map(|tour| path_cost(tour, grid, objective, None)).fold(f32::INFINITY, f32::min)
+    }
+
+    /// Every edge into or out of `city` missing, so no tour exists
+    fn isolate(mut grid: Vec<Vec<f32>>, city: usize) -> Vec<Vec<f32>> {
+        for (other, row) in grid.iter_mut().enumerate() {
+            if other != city {
+                row[city] = f32::INFINITY;
+            }
+        }
+        for (other, length) in grid[city].iter_mut().enumerate() {
+            if other != city {
+                *length = f32::INFINITY;
+            }
+        }
+        grid
+    }
+
+    fn square() -> Vec<Vec<f32>> {
+        grid_of(&[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0), (0.5, 2.0)])
+    }
+
+    /// Seeded random cities, and with `asymmetric` each direction stretched independently so the
+    /// closing edge and the tour's direction both matter
+    fn random_instance(n: usize, seed: u64, asymmetric: bool) -> (Vec<Point>, Vec<Vec<f32>>) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let points = generate_points_with(n, 100, false, &mut rng).unwrap();
+        let mut grid = calculate_edge_grid(points.clone()).unwrap();
+        if asymmetric {
+            for distance in grid.iter_mut().flatten() {
+                *distance *= rng.gen_range(1.0..1.5);
+            }
+        }
+        (points, grid)
+    }
+
+    /// Same optimum up to the rounding of summing the edges in a different order
+    fn same_length(a: f32, b: f32) -> bool {
+        (a - b).abs() <= 1e-4 * a.max(1.0)
+    }
+
+    #[test]
+    fn exact_solvers_agree_on_small_random_instances() {
+        for n in 1..=8 {
+            for seed in 0..30 {
+                for asymmetric in [false, true] {
+                    let (points, grid) = random_instance(n, seed, asymmetric);
+                    let case = format!("n={n} seed={seed} asymmetric={asymmetric}");
+                    let lengths = all_tours(n).into_iter().map(|tour| tour_length(&grid, &tour).unwrap());
+                    let enumerated = lengths.fold(f32::INFINITY, f32::min);
+
+                    let (deep, deep_length, _) = brute_force_to_depth(&grid, None).unwrap();
+                    let (counted, counted_length, _) = branch_and_bound_counted(&grid).unwrap();
+                    let mut results = vec![
+                        ("brute_force", brute_force(&grid).unwrap()),
+                        ("brute_force_to_depth", (deep, deep_length)),
+                        ("brute_force_with_precedences", brute_force_with_precedences(&grid, &[]).unwrap()),
+                        ("held_karp", held_karp(&grid, false).unwrap()),
+                        ("held_karp low_memory", held_karp(&grid, true).unwrap()),
+                        ("branch_and_bound_anytime", branch_and_bound_anytime(&grid, |_, _| {}).unwrap()),
+                        ("branch_and_bound_counted", (counted, counted_length)),
+                    ];
+                    // the hull shortcut assumes the Euclidean grid of the points
+                    if !asymmetric {
+                        results.push(("solve_auto", solve_auto(&points, &grid).unwrap()));
+                    }
+                    for (solver, (tour, length)) in results {
+                        validate_tour(&tour, n).unwrap();
+                        assert!(same_length(enumerated, length), "{solver} gave {length}, enumeration {enumerated}, {case}");
+                        let walked = tour_length(&grid, &tour).unwrap();
+                        assert!(same_length(walked, length), "{solver} reported {length} for a tour of {walked}, {case}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn depth_one_branches_once_then_completes_greedily() {
+        let grid = seeded(8, 3);
+        let (optimum, optimal_length) = brute_force(&grid).unwrap();
+
+        let (tour, length, truncated) = brute_force_to_depth(&grid, Some(1)).unwrap();
+        validate_tour(&tour, 8).unwrap();
+        assert_eq!(tour[0], 0);
+        assert_eq!(length, tour_length(&grid, &tour).unwrap());
+        assert!(length >= optimal_length);
+        assert!(truncated);
+
+        // deep enough to branch on every city is plain brute force
+        assert_eq!(brute_force_to_depth(&grid, Some(8)).unwrap(), (optimum.clone(), optimal_length, false));
+        assert_eq!(brute_force_to_depth(&grid, None).unwrap(), (optimum, optimal_length, false));
+    }
+
+    #[test]
+    fn precedence_changes_the_optimum() {
+        // a one-way street round the square: counter-clockwise costs double
+        let mut grid = grid_of(&[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0), (0.5, 0.4)]);
+        for i in 0..4 {
+            grid[(i + 1) % 4][i] *= 2.0;
+        }
+        let (free, free_length) = brute_force(&grid).unwrap();
+        let position = |tour: &[usize], city| tour.iter().position(|&c| c == city).unwrap();
+        assert!(position(&free, 1) < position(&free, 3));
+
+        let (constrained, length) = brute_force_with_precedences(&grid, &[(3, 1)]).unwrap();
+        assert!(position(&constrained, 3) < position(&constrained, 1));
+        assert!(length > free_length);
+        assert_eq!(length, unpruned_precedence_min(&grid, 3, 1));
+    }
+
+    #[test]
+    fn cyclic_precedences_are_infeasible() {
+        let grid = square();
+        let cyclic = [(1, 2), (2, 3), (3, 1)];
+        assert!(matches!(brute_force_with_precedences(&grid, &cyclic), Err(TspError::Infeasible)));
+        assert!(matches!(brute_force_with_precedences(&grid, &[(2, 0)]), Err(TspError::Infeasible)));
+    }
+
+    fn unpruned_precedence_min(grid: &[Vec<f32>], before: usize, after: usize) -> f32 {
+        all_tours(grid.len())
+            .iter()
+            .filter(|tour| tour.iter().position(|&c| c == before) < tour.iter().position(|&c| c == after))
+            .map(|tour| tour_length(grid, tour).unwrap())
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    #[test]
+    fn estimate_grows_factorially() {
+        assert!(estimate_brute_force_time(12) > Duration::ZERO);
+        // one more city multiplies the (n-1)!/2 tours by n
+        for n in 12..16 {
+            let ratio = estimate_brute_force_time(n + 1).as_secs_f64() / estimate_brute_force_time(n).as_secs_f64();
+            assert!((ratio / n as f64 - 1.0).abs() < 1e-3, "{n} to {} cities: {ratio}", n + 1);
+        }
+    }
+
+    #[test]
+    fn service_times_move_the_latency_optimum_only() {
+        let grid = seeded(7, 5);
+        let (prompt, _) = brute_force_with_objective(&grid, &Objective::MinimumLatency, None).unwrap();
+        // a slow first stop holds up everyone after it, so it should go last instead
+        let slow = prompt[1];
+        let mut service = vec![1.0; 7];
+        service[slow] = 1000.0;
+
+        let (delayed, _) = brute_force_with_objective(&grid, &Objective::MinimumLatency, Some(&service)).unwrap();
+        assert_ne!(delayed, prompt);
+        assert_eq!(delayed[6], slow);
+
+        let (shortest, length) = brute_force(&grid).unwrap();
+        let (tour, cost) = brute_force_with_objective(&grid, &Objective::TotalDistance, Some(&service)).unwrap();
+        assert_eq!(tour, shortest);
+        assert!((cost - length - service.iter().sum::<f32>()).abs() < 1e-2);
+    }
+
+    #[test]
+    fn fixed_prefix_goes_first_and_the_rest_is_optimal() {
+        // city 2 is across the map from city 0
+        let grid = grid_of(&[(0.0, 0.0), (2.0, 0.0), (9.0, 8.0), (0.0, 3.0), (5.0, 1.0), (2.0, 6.0), (8.0, 4.0)]);
+        let (optimum, _) = brute_force(&grid).unwrap();
+        assert!(optimum[1] != 2 && optimum[6] != 2, "city 2 should be out of place in {optimum:?}");
+
+        let (tour, length) = optimize_with_fixed_prefix(&grid, &[0, 2]).unwrap();
+        assert_eq!(tour[..2], [0, 2]);
+        let best_with_prefix = all_tours(7)
+            .iter()
+            .filter(|tour| tour[1] == 2)
+            .map(|tour| tour_length(&grid, tour).unwrap())
+            .fold(f32::INFINITY, f32::min);
+        assert_eq!(length, best_with_prefix);
+    }
+
+    #[test]
+    fn exact_integer_search_matches_the_float_one() {
+        for seed in 0..5 {
+            let rounded = round_grid(&seeded(7, seed));
+            let (tour, length) = brute_force_exact(&rounded).unwrap();
+            validate_tour(&tour, 7).unwrap();
+            let total: u64 = (0..7).map(|i| rounded[tour[i]][tour[(i + 1) % 7]]).sum();
+            assert_eq!(length, total);
+
+            let float: Vec<Vec<f32>> = rounded.iter().map(|row| row.iter().map(|&d| d as f32).collect()).collect();
+            assert_eq!(brute_force(&float).unwrap().1, length as f32);
+        }
+    }
+
+    #[test]
+    fn required_adjacency_is_the_best_tour_through_that_edge() {
+        let grid = seeded(6, 4);
+        let (optimal, optimum) = brute_force(&grid).unwrap();
+        let adjacent = |tour: &[usize], i: usize, j: usize| (0..6).any(|k| {
+            let (a, b) = (tour[k], tour[(k + 1) % 6]);
+            (a, b) == (i, j) || (a, b) == (j, i)
+        });
+
+        for i in 0..6 {
+            for j in i + 1..6 {
+                let (tour, length) = best_tour_with_adjacency(&grid, i, j).unwrap();
+                assert!(adjacent(&tour, i, j), "{i}-{j} not adjacent in {tour:?}");
+                let expected = all_tours(6)
+                    .iter()
+                    .filter(|tour| adjacent(tour, i, j))
+                    .map(|tour| tour_length(&grid, tour).unwrap())
+                    .fold(f32::INFINITY, f32::min);
+                assert!((length - expected).abs() <= expected * 1e-5, "{i}-{j}");
+                if adjacent(&optimal, i, j) {
+                    assert!((length - optimum).abs() <= optimum * 1e-5, "{i}-{j}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn branch_and_bound_with_required_edges_matches_brute_force() {
+        let grid = seeded(7, 2);
+        for required in [vec![], vec![(0, 3)], vec![(1, 2), (2, 5), (6, 4)], vec![(3, 0), (0, 1), (1, 2), (2, 3)]] {
+            let expected = brute_force_with_required_edges(&grid, &required).map(|(_, length)| length);
+            let found = branch_and_bound_with_required_edges(&grid, &required);
+            match (found, expected) {
+                (Ok((tour, length)), Ok(expected)) => {
+                    let edges = tour_edges(&tour);
+                    assert!(required.iter().all(|&(a, b)| edges.contains(&(a.min(b), a.max(b)))), "{required:?}");
+                    assert!((length - expected).abs() <= expected * 1e-5, "{required:?}");
+                }
+                (found, expected) => {
+                    let both_infeasible = matches!((found, expected), (Err(TspError::Infeasible), Err(TspError::Infeasible)));
+                    assert!(both_infeasible, "{required:?}");
+                }
+            }
+        }
+        let three_at_one = [(1, 0), (1, 2), (1, 3)];
+        assert!(matches!(branch_and_bound_with_required_edges(&grid, &three_at_one), Err(TspError::Infeasible)));
+    }
+
+    #[test]
+    fn bitmask_search_matches_full_enumeration() {
+        for n in 1..=8 {
+            let mut grid = seeded(n, n as u64);
+            let (tour, length) = brute_force(&grid).unwrap();
+            validate_tour(&tour, n).unwrap();
+            let expected = unpruned_min(&grid, &Objective::TotalDistance);
+            assert!((length - expected).abs() <= expected * 1e-5, "n = {n}");
+
+            grid[0][n - 1] *= 3.0;
+            let (_, length) = brute_force(&grid).unwrap();
+            let expected = unpruned_min(&grid, &Objective::TotalDistance);
+            assert!((length - expected).abs() <= expected * 1e-5, "asymmetric n = {n}");
+        }
+
+        let too_many = vec![vec![1.0; MAX_CITIES + 1]; MAX_CITIES + 1];
+        assert!(matches!(brute_force(&too_many), Err(TspError::TooManyCities(MAX_CITIES, _))));
+    }
+
+    #[test]
+    fn turn_penalty_trades_a_little_length_for_straighter_legs() {
+        let coordinates = [(2.0, 3.0), (4.0, 1.0), (0.0, 5.0), (6.0, 5.0), (1.0, 2.0)];
+        let grid = grid_of(&coordinates);
+        let points: Vec<Point> = coordinates.iter().map(|&(x, y)| Point { x, y, label: None }).collect();
+        let turning = |tour: &[usize]| tour_turn_angles(&points, tour).iter().sum::<f32>();
+
+        let (shortest, length) = brute_force(&grid).unwrap();
+        let unpenalized = Objective::TurnPenalty { points: points.clone(), lambda: 0.0 };
+        assert_eq!(brute_force_with_objective(&grid, &unpenalized, None).unwrap().0, shortest);
+
+        let objective = Objective::TurnPenalty { points: points.clone(), lambda: 0.01 };
+        let (smooth, cost) = brute_force_with_objective(&grid, &objective, None).unwrap();
+        assert!(tour_length(&grid, &smooth).unwrap() > length);
+        assert!(turning(&smooth) < turning(&shortest));
+        assert!((cost - unpruned_min(&grid, &objective)).abs() <= cost * 1e-5);
+    }
+
+    #[test]
+    fn rush_hour_picks_the_direction_that_saves_the_long_leg() {
+        let grid = vec![
+            vec![0.0, 10.0, 100.0, 5.0],
+            vec![10.0, 0.0, 1.0, 100.0],
+            vec![100.0, 1.0, 0.0, 1.0],
+            vec![5.0, 100.0, 1.0, 0.0],
+        ];
+        let rush_hour = |clock: f32| if clock >= 10.0 { 2.0 } else { 1.0 };
+
+        // forwards the 10 is driven first and everything after it doubles; backwards it starts at 7
+        assert_eq!(time_dependent_cost(&[0, 1, 2, 3], &grid, &rush_hour), 24.0);
+        assert_eq!(time_dependent_cost(&[0, 3, 2, 1], &grid, &rush_hour), 17.0);
+        assert_eq!(brute_force_time_dependent(&grid, &rush_hour).unwrap(), (vec![0, 3, 2, 1], 17.0));
+
+        let grid = seeded(6, 12);
+        let slowing = |clock: f32| 1.0 + clock / 100.0;
+        let (tour, cost) = brute_force_time_dependent(&grid, &slowing).unwrap();
+        let costs = all_tours(6).into_iter().map(|t| time_dependent_cost(&t, &grid, &slowing));
+        assert_eq!(cost, costs.fold(f32::INFINITY, f32::min));
+        assert_eq!(time_dependent_cost(&tour, &grid, &slowing), cost);
+    }
+
+    #[test]
+    fn equal_length_tours_prefer_the_shorter_longest_edge() {
+        // 0-1-2-3 and 0-1-3-2 both cost 12, with longest edges 5 and 4
+        let grid = vec![
+            vec![0.0, 2.0, 4.0, 3.0],
+            vec![2.0, 0.0, 5.0, 4.0],
+            vec![4.0, 5.0, 0.0, 2.0],
+            vec![3.0, 4.0, 2.0, 0.0],
+        ];
+        assert_eq!(brute_force(&grid).unwrap(), (vec![0, 1, 2, 3], 12.0));
+        assert_eq!(brute_force_with_edge_tiebreak(&grid).unwrap(), (vec![0, 1, 3, 2], 12.0));
+
+        let grid = seeded(7, 2);
+        assert_eq!(brute_force_with_edge_tiebreak(&grid).unwrap().1, brute_force(&grid).unwrap().1);
+    }
+
+    #[test]
+    fn a_tour_exists_under_every_limit_above_the_optimum() {
+        for seed in 0..5 {
+            let grid = seeded(7, seed);
+            let (_, optimum) = brute_force(&grid).unwrap();
+
+            let tour = exists_tour_under(&grid, optimum * 1.001).unwrap().unwrap();
+            validate_tour(&tour, 7).unwrap();
+            assert!(tour_length(&grid, &tour).unwrap() < optimum * 1.001, "seed {seed}");
+            assert_eq!(exists_tour_under(&grid, optimum * 0.999).unwrap(), None, "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn direction_penalty_stays_in_fewer_aisles() {
+        let coordinates = [(4.0, 4.0), (0.0, 1.0), (2.0, 4.0), (1.0, 2.0), (0.0, 2.0), (4.0, 1.0)];
+        let points: Vec<Point> = coordinates.iter().map(|&(x, y)| Point { x, y, label: None }).collect();
+        let grid = calculate_edge_grid_metric(&points, Metric::Manhattan).unwrap();
+
+        let (shortest, length) = brute_force(&grid).unwrap();
+        assert_eq!(length, 14.0);
+        let objective = Objective::DirectionChanges { points: points.clone(), penalty: 2.0 };
+        let (picked, cost) = brute_force_with_objective(&grid, &objective, None).unwrap();
+
+        assert_eq!(tour_length(&grid, &picked).unwrap(), 16.0);
+        assert_eq!(tour_axis_changes(&points, &picked), 4);
+        assert!(tour_axis_changes(&points, &shortest) > 4);
+        assert_eq!(cost, 24.0);
+        assert_eq!(cost, unpruned_min(&grid, &objective));
+    }
+
+    #[test]
+    fn anytime_incumbents_improve_down_to_the_optimum() {
+        for seed in 0..5 {
+            let grid = seeded(9, seed);
+            let mut incumbents = Vec::new();
+            let (tour, length) = branch_and_bound_anytime(&grid, |tour, length| {
+                validate_tour(tour, 9).unwrap();
+                incumbents.push((tour.to_vec(), length));
+            })
+            .unwrap();
+
+            assert!(incumbents.windows(2).all(|pair| pair[1].1 < pair[0].1), "seed {seed}");
+            assert_eq!(incumbents.last(), Some(&(tour, length)), "seed {seed}");
+            assert!((length - brute_force(&grid).unwrap().1).abs() <= length * 1e-5, "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn warm_start_from_the_optimum_proves_it_with_fewer_nodes() {
+        let grid = seeded(10, 3);
+        let (optimal, optimum, cold_nodes) = branch_and_bound_counted(&grid).unwrap();
+
+        let rotated = rotate_to(&optimal, 4);
+        assert_eq!(branch_and_bound_warm(&grid, &rotated).unwrap(), (optimal.clone(), optimum));
+        let mut warm = BranchAndBound::new(&grid, optimum).unwrap();
+        warm.best = optimal.clone();
+        warm.run();
+        assert!(warm.nodes < cold_nodes, "{} warm nodes, {cold_nodes} cold", warm.nodes);
+
+        let (tour, length) = branch_and_bound_warm(&grid, &(0..10).collect::<Vec<_>>()).unwrap();
+        assert!((length - optimum).abs() <= optimum * 1e-5);
+        assert_eq!(canonical_tour(&tour), canonical_tour(&optimal));
+    }
+
+    #[test]
+    fn nearest_first_branching_keeps_the_optimum_with_fewer_leaves() {
+        for seed in 0..5 {
+            let grid = seeded(9, seed);
+            let mut search = BranchAndBound::new(&grid, f32::INFINITY).unwrap();
+            search.run();
+
+            let optimum = brute_force(&grid).unwrap().1;
+            assert!((search.limit - optimum).abs() <= optimum * 1e-5, "seed {seed}");
+            assert!((search.leaves as f64) < leaf_count(9) / 10.0, "seed {seed}: {} leaves", search.leaves);
+        }
+    }
+
+    #[test]
+    fn bottleneck_first_matches_enumeration() {
+        let longest = |grid: &[Vec<f32>], tour: &[usize]| bottleneck_edge(tour, grid).2;
+        let mut traded = false;
+        for seed in 0..10 {
+            let grid = seeded(7, seed);
+            let (tour, bottleneck, length) = bottleneck_then_distance(&grid).unwrap();
+            validate_tour(&tour, 7).unwrap();
+
+            let tours = all_tours(7);
+            let expected = tours.iter().map(|t| longest(&grid, t)).fold(f32::INFINITY, f32::min);
+            assert_eq!(bottleneck, expected, "seed {seed}");
+            assert_eq!(longest(&grid, &tour), bottleneck, "seed {seed}");
+            let shortest = tours
+                .iter()
+                .filter(|t| longest(&grid, t) == bottleneck)
+                .map(|t| tour_length(&grid, t).unwrap())
+                .fold(f32::INFINITY, f32::min);
+            assert!((length - shortest).abs() <= length * 1e-5, "seed {seed}");
+
+            let (optimal, _) = brute_force(&grid).unwrap();
+            traded |= longest(&grid, &optimal) > bottleneck;
+        }
+        assert!(traded, "no instance where the shortest tour has a longer longest edge");
+    }
+
+    #[test]
+    fn k_best_lists_each_cycle_once() {
+        let grid = seeded(6, 7);
+        let best = k_best_tours(&grid, 10).unwrap();
+        assert_eq!(best.len(), 10);
+        let distinct: HashSet<Vec<usize>> = best.iter().map(|(tour, _)| canonical_tour(tour)).collect();
+        assert_eq!(distinct.len(), 10);
+        assert!(best.iter().all(|(tour, _)| tour[0] == 0));
+        assert!(best.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+        assert_eq!(best[0].1, brute_force(&grid).unwrap().1);
+
+        let mut lengths: Vec<f32> = all_tours(6).iter().map(|tour| tour_length(&grid, tour).unwrap()).collect();
+        lengths.sort_by(f32::total_cmp);
+        // every cycle was enumerated in both directions
+        let expected: Vec<f32> = lengths.iter().step_by(2).take(10).copied().collect();
+        for ((_, length), expected) in best.iter().zip(expected) {
+            assert!((length - expected).abs() <= expected * 1e-5);
+        }
+
+        // 5!/2 cycles on 6 cities
+        assert_eq!(k_best_tours(&grid, 100).unwrap().len(), 60);
+        let mut one_way = grid.clone();
+        one_way[0][1] += 50.0;
+        assert_eq!(k_best_tours(&one_way, 100).unwrap().len(), 60);
+    }
+
+    #[test]
+    fn open_and_flat_returns_search_mirrors_too() {
+        let grid = grid_of(&[(0.0, 0.0), (10.0, 0.0), (2.0, 0.0), (1.0, 0.0)]);
+        for policy in [ReturnPolicy::None, ReturnPolicy::Flat(3.0)] {
+            let objective = Objective::Return(policy);
+            let (tour, cost) = brute_force_with_objective(&grid, &objective, None).unwrap();
+            assert_eq!(cost, unpruned_min(&grid, &objective));
+            assert_eq!(path_cost(&tour, &grid, &objective, None), cost);
+        }
+
+        let (tour, cost) = brute_force_with_objective(&grid, &Objective::Return(ReturnPolicy::None), None).unwrap();
+        assert_eq!((tour, cost), (vec![0, 3, 2, 1], 10.0));
+    }
+
+    #[test]
+    fn fixed_prefix_with_no_completion_is_infeasible() {
+        let mut grid = square();
+        grid[0][2] = f32::INFINITY;
+        grid[2][0] = f32::INFINITY;
+        assert!(matches!(optimize_with_fixed_prefix(&grid, &[0, 2]), Err(TspError::Infeasible)));
+        assert!(matches!(optimize_with_fixed_prefix(&isolate(square(), 3), &[]), Err(TspError::Infeasible)));
+    }
+
+    #[test]
+    fn objective_with_no_finite_tour_is_infeasible() {
+        let grid = isolate(square(), 2);
+        let result = brute_force_with_objective(&grid, &Objective::TotalDistance, None);
+        assert!(matches!(result, Err(TspError::Infeasible)));
+    }
+
+    #[test]
+    fn depth_limited_search_with_no_finite_tour_is_infeasible() {
+        let grid = isolate(square(), 4);
+        for max_depth in [None, Some(0), Some(2)] {
+            assert!(matches!(brute_force_to_depth(&grid, max_depth), Err(TspError::Infeasible)));
+        }
+    }
+
+    #[test]
+    fn checkpointed_search_with_no_finite_tour_is_infeasible() {
+        let path = std::env::temp_dir().join(format!("tsp-infeasible-{}.json", std::process::id()));
+        let grid = isolate(square(), 1);
+        assert!(matches!(brute_force_checkpointed(&grid, &path, 3), Err(TspError::Infeasible)));
+        let checkpoint = Checkpoint::load(&path).unwrap();
+        assert!(matches!(resume_brute_force(checkpoint, &path, 3), Err(TspError::Infeasible)));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
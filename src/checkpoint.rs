@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::error::TspError;
+
+/// Snapshot of a brute-force search, enough to carry on from where it stopped. Tours are
+/// enumerated in lexicographic order, so the last evaluated tour marks everything explored so far.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// The instance being solved, so a resume needs nothing else
+    #[serde(with = "missing_edges")]
+    pub grid: Vec<Vec<f32>>,
+    pub best: Vec<usize>,
+    /// Infinite until a tour is found
+    #[serde(with = "missing_length")]
+    pub min: f32,
+    /// Last complete tour evaluated
+    pub position: Vec<usize>,
+    /// Complete tours evaluated so far
+    pub nodes: u64,
+}
+
+impl Checkpoint {
+    pub fn save(&self, path: &Path) -> Result<(), TspError> {
+        let json = serde_json::to_string(self).map_err(io::Error::from)?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, TspError> {
+        let json = fs::read_to_string(path)?;
+        let checkpoint = serde_json::from_str(&json).map_err(io::Error::from)?;
+        Ok(checkpoint)
+    }
+}
+
+// JSON has no infinity: serde_json writes it as null and then refuses to read null back as an
+// f32, so missing edges (and a `min` with no tour found yet) are stored as explicit nulls
+
+mod missing_edges {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(grid: &[Vec<f32>], serializer: S) -> Result<S::Ok, S::Error> {
+        let rows: Vec<Vec<Option<f32>>> =
+            grid.iter().map(|row| row.iter().map(|&d| d.is_finite().then_some(d)).collect()).collect();
+        rows.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Vec<f32>>, D::Error> {
+        let rows = Vec::<Vec<Option<f32>>>::deserialize(deserializer)?;
+        Ok(rows.into_iter().map(|row| row.into_iter().map(|d| d.unwrap_or(f32::INFINITY)).collect()).collect())
+    }
+}
+
+mod missing_length {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(length: &f32, serializer: S) -> Result<S::Ok, S::Error> {
+        length.is_finite().then_some(*length).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+        Ok(Option::<f32>::deserialize(deserializer)?.unwrap_or(f32::INFINITY))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::brute_force::{brute_force, brute_force_checkpointed, resume_brute_force};
+    use crate::grid::calculate_edge_grid;
+    use crate::point::generate_points_with;
+    use crate::tour::tour_length;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn sparse_grid() -> Vec<Vec<f32>> {
+        let inf = f32::INFINITY;
+        vec![
+            vec![0.0, 2.0, inf, 3.0, 1.0],
+            vec![2.0, 0.0, 4.0, inf, 2.5],
+            vec![inf, 4.0, 0.0, 1.5, 6.0],
+            vec![3.0, inf, 1.5, 0.0, 5.0],
+            vec![1.0, 2.5, 6.0, 5.0, 0.0],
+        ]
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("tsp-{name}-{}.json", std::process::id()))
+    }
+
+    #[test]
+    fn resuming_a_saved_search_finds_the_same_optimum() {
+        let points = generate_points_with(7, 100, false, &mut StdRng::seed_from_u64(4)).unwrap();
+        let grid = calculate_edge_grid(points).unwrap();
+        let path = temp_path("resume");
+        let expected = brute_force(&grid).unwrap();
+        assert_eq!(brute_force_checkpointed(&grid, &path, 5).unwrap(), expected);
+
+        // saved right after the first tour, which is not the optimum
+        let first: Vec<usize> = (0..7).collect();
+        let min = tour_length(&grid, &first).unwrap();
+        assert!(min > expected.1);
+        let early = Checkpoint { grid, best: first.clone(), min, position: first, nodes: 1 };
+        early.save(&path).unwrap();
+        let loaded = Checkpoint::load(&path).unwrap();
+        assert_eq!(loaded, early);
+        assert_eq!(resume_brute_force(loaded, &path, 5).unwrap(), expected);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn sparse_grid_round_trips() {
+        let path = temp_path("round-trip");
+        let checkpoint =
+            Checkpoint { grid: sparse_grid(), best: Vec::new(), min: f32::INFINITY, position: Vec::new(), nodes: 0 };
+        checkpoint.save(&path).unwrap();
+        assert_eq!(Checkpoint::load(&path).unwrap(), checkpoint);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn resuming_a_sparse_search_finds_the_same_optimum() {
+        let grid = sparse_grid();
+        let path = temp_path("sparse-resume");
+        let expected = brute_force(&grid).unwrap();
+        assert_eq!(brute_force_checkpointed(&grid, &path, 1).unwrap(), expected);
+
+        // saved right after the first tour, which is not the optimum
+        let first = vec![0, 1, 2, 3, 4];
+        let early = Checkpoint { grid, best: first.clone(), min: 13.5, position: first, nodes: 1 };
+        early.save(&path).unwrap();
+        assert_eq!(resume_brute_force(Checkpoint::load(&path).unwrap(), &path, 1).unwrap(), expected);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,75 @@
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::error::TspError;
+
+/// `solve` settings kept in a JSON file for reproducible runs, e.g.
+/// `{"algorithm": "two-opt", "metric": "manhattan", "seed": 7}`. Keys are the long flag names;
+/// a flag given on the command line overrides the file's value.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct SolveConfig {
+    pub algorithm: Option<String>,
+    pub metric: Option<String>,
+    pub format: Option<String>,
+    pub seed: Option<u64>,
+    pub output_dir: Option<String>,
+    pub checkpoint: Option<String>,
+    pub checkpoint_every: Option<u64>,
+    pub warm_tour: Option<String>,
+    pub stdin: bool,
+    pub force: bool,
+    pub unicode: bool,
+}
+
+impl SolveConfig {
+    pub fn load(path: &Path) -> Result<Self, TspError> {
+        let json = fs::read_to_string(path)?;
+        let config = serde_json::from_str(&json).map_err(io::Error::from)?;
+        Ok(config)
+    }
+
+    /// The settings as (flag name, value) pairs, as if typed on the command line; switches that
+    /// are off are left out, as their flags would be
+    pub fn flags(&self) -> Vec<(&'static str, String)> {
+        let values = [
+            ("algorithm", self.algorithm.clone()),
+            ("metric", self.metric.clone()),
+            ("format", self.format.clone()),
+            ("seed", self.seed.map(|seed| seed.to_string())),
+            ("output-dir", self.output_dir.clone()),
+            ("checkpoint", self.checkpoint.clone()),
+            ("checkpoint-every", self.checkpoint_every.map(|every| every.to_string())),
+            ("warm-tour", self.warm_tour.clone()),
+        ];
+        let switches = [("stdin", self.stdin), ("force", self.force), ("unicode", self.unicode)];
+
+        values
+            .into_iter()
+            .filter_map(|(name, value)| value.map(|value| (name, value)))
+            .chain(switches.into_iter().filter(|(_, on)| *on).map(|(name, _)| (name, "true".to_owned())))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_file_reads_as_flags() {
+        let path = std::env::temp_dir().join(format!("tsp-config-{}.json", std::process::id()));
+        fs::write(&path, r#"{"algorithm": "held-karp", "checkpoint-every": 500, "force": true}"#).unwrap();
+        let config = SolveConfig::load(&path).unwrap();
+
+        assert_eq!(config.algorithm.as_deref(), Some("held-karp"));
+        let expected = [("algorithm", "held-karp"), ("checkpoint-every", "500"), ("force", "true")];
+        assert_eq!(config.flags(), expected.map(|(name, value)| (name, value.to_owned())));
+
+        fs::write(&path, r#"{"algoritm": "held-karp"}"#).unwrap();
+        assert!(SolveConfig::load(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,94 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::time::{Duration, Instant};
+
+use crate::brute_force::branch_and_bound_counted;
+use crate::error::TspError;
+use crate::grid::calculate_edge_grid;
+use crate::point::generate_points_with;
+use crate::MAP_WIDTH;
+
+/// One seeded instance solved to optimality
+pub struct Trial {
+    /// Recreates the instance, e.g. `generate --cities N --seed SEED`
+    pub seed: u64,
+    pub length: f32,
+    pub time: Duration,
+    /// Branch-and-bound nodes expanded proving the optimum
+    pub nodes: u64,
+}
+
+/// Solves the instances of `cities` cities seeded `first_seed`, `first_seed + 1`, ... exactly with
+/// branch and bound, one per trial, to compare how hard same-sized instances are
+pub fn difficulty(cities: usize, trials: usize, first_seed: u64) -> Result<Vec<Trial>, TspError> {
+    if trials == 0 {
+        return Err(TspError::EmptyInput);
+    }
+
+    (first_seed..first_seed + trials as u64)
+        .map(|seed| {
+            let points = generate_points_with(cities, MAP_WIDTH, false, &mut StdRng::seed_from_u64(seed))?;
+            let grid = calculate_edge_grid(points)?;
+            let start = Instant::now();
+            let (_, length, nodes) = branch_and_bound_counted(&grid)?;
+            Ok(Trial { seed, length, time: start.elapsed(), nodes })
+        })
+        .collect()
+}
+
+/// The trial that took the most nodes; the first of them on a tie
+pub fn hardest(trials: &[Trial]) -> Option<&Trial> {
+    trials.iter().rev().max_by_key(|trial| trial.nodes)
+}
+
+/// Min, mean and max of the optimal lengths, solve times and node counts, then the hardest seed
+pub fn format_difficulty(trials: &[Trial]) -> String {
+    let Some(worst) = hardest(trials) else {
+        return String::new();
+    };
+    let count = trials.len() as f64;
+    let stats = |values: Vec<f64>| {
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        (min, values.iter().sum::<f64>() / count, max)
+    };
+
+    let mut out = format!("{:<10} {:>14} {:>14} {:>14}\n", "", "min", "mean", "max");
+    let (min, mean, max) = stats(trials.iter().map(|t| t.length as f64).collect());
+    out.push_str(&format!("{:<10} {min:>14.1} {mean:>14.1} {max:>14.1}\n", "length"));
+    let (min, mean, max) = stats(trials.iter().map(|t| t.time.as_secs_f64() * 1000.0).collect());
+    let (min, mean, max) = (format!("{min:.2}ms"), format!("{mean:.2}ms"), format!("{max:.2}ms"));
+    out.push_str(&format!("{:<10} {min:>14} {mean:>14} {max:>14}\n", "time"));
+    let (min, mean, max) = stats(trials.iter().map(|t| t.nodes as f64).collect());
+    out.push_str(&format!("{:<10} {min:>14.0} {mean:>14.0} {max:>14.0}\n", "nodes"));
+    out.push_str(&format!(
+        "\nHardest: seed {} with {} nodes in {:?}, optimal length {:.1}\n",
+        worst.seed, worst.nodes, worst.time, worst.length
+    ));
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trial(seed: u64, nodes: u64) -> Trial {
+        Trial { seed, length: 1.0, time: Duration::ZERO, nodes }
+    }
+
+    #[test]
+    fn hardest_is_the_first_trial_with_the_most_nodes() {
+        let trials = [trial(3, 40), trial(4, 90), trial(5, 12), trial(6, 90)];
+        assert_eq!(hardest(&trials).map(|t| t.seed), Some(4));
+        assert!(hardest(&[]).is_none());
+
+        let trials = difficulty(7, 4, 10).unwrap();
+        assert_eq!(trials.iter().map(|t| t.seed).collect::<Vec<_>>(), vec![10, 11, 12, 13]);
+        let most = trials.iter().map(|t| t.nodes).max().unwrap();
+        let worst = hardest(&trials).unwrap();
+        assert_eq!(worst.nodes, most);
+        assert!(format_difficulty(&trials).contains(&format!("Hardest: seed {} with {most} nodes", worst.seed)));
+        assert!(matches!(difficulty(7, 0, 10), Err(TspError::EmptyInput)));
+    }
+}
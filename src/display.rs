@@ -0,0 +1,614 @@
+use log::debug;
+use std::io::{self, IsTerminal};
+use std::time::{Duration, Instant};
+
+use crate::grid::is_symmetric;
+use crate::point::Point;
+use crate::tour::{edge_difference, tour_edges, Edge};
+use crate::{GRAPH_PIXELS, MAP_WIDTH, SHOW_CAPTION};
+
+pub fn format_grid(grid: &[Vec<f32>]) -> String {
+    let n = grid.len();
+    let mut out = String::from("\nGrid:\n\n");
+
+    out.push_str("    ");
+    for j in 0..n {
+        out.push_str(&format!(" {:>5}", j));
+    }
+    out.push('\n');
+    out.push_str("    ");
+    for _ in 0..n {
+        out.push_str("______");
+    }
+    out.push('\n');
+
+    for (i, row) in grid.iter().enumerate() {
+        out.push_str(&format!("{:^3} |", i));
+
+        for distance in row {
+            out.push_str(&format!("{0:>5.1} ", distance));
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+/// One character per distance, shaded from `.` for the shortest edge to `@` for the longest
+pub fn format_grid_heatmap(grid: &[Vec<f32>]) -> String {
+    const SHADES: &[u8] = b".:-=+*#%@";
+    let (mut min, mut max) = (f32::MAX, 0 as f32);
+    for (_, _, distance) in off_diagonal(grid) {
+        min = min.min(distance);
+        max = max.max(distance);
+    }
+    let range = if max > min { max - min } else { 1.0 };
+
+    let mut out = String::from("\nHeatmap:\n\n");
+    for (i, row) in grid.iter().enumerate() {
+        out.push_str(&format!("{:^3} |", i));
+        for (j, &distance) in row.iter().enumerate() {
+            let shade = if i == j { b' ' } else { SHADES[((distance - min) / range * (SHADES.len() - 1) as f32).round() as usize] };
+            out.push(shade as char);
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Widest bar of a histogram, in characters
+const HISTOGRAM_WIDTH: usize = 40;
+
+/// `buckets` equal-width ranges from the smallest to the largest value, one `#` bar per range
+/// scaled so the fullest is `HISTOGRAM_WIDTH` long, each followed by its count
+pub fn format_histogram(values: &[f32], buckets: usize) -> String {
+    let mut out = String::new();
+    if values.is_empty() || buckets == 0 {
+        return out;
+    }
+
+    let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let width = if max > min { (max - min) / buckets as f32 } else { 1.0 };
+    let mut counts = vec![0; buckets];
+    for value in values {
+        counts[(((value - min) / width) as usize).min(buckets - 1)] += 1;
+    }
+
+    let fullest = *counts.iter().max().unwrap();
+    for (i, count) in counts.iter().enumerate() {
+        let low = min + i as f32 * width;
+        let bar = "#".repeat(count * HISTOGRAM_WIDTH / fullest);
+        out.push_str(&format!("{low:>8.1} - {:>8.1} | {bar} {count}\n", low + width));
+    }
+
+    out
+}
+
+/// How the instance's distances are spread: clustered cities show up as a heap of short edges
+/// and a second hump of long ones, uniformly spread cities as one broad hump
+pub fn display_edge_histogram(grid: &[Vec<f32>], buckets: usize) {
+    print!("{}", format_histogram(&edge_lengths(grid), buckets));
+}
+
+/// Every pairwise distance, skipping the diagonal and missing (infinite) edges. A symmetric grid
+/// contributes only its upper triangle, so each pair counts once: n(n-1)/2 values.
+pub fn edge_lengths(grid: &[Vec<f32>]) -> Vec<f32> {
+    let symmetric = is_symmetric(grid);
+    off_diagonal(grid)
+        .filter(|&(i, j, distance)| !(symmetric && j < i) && distance.is_finite())
+        .map(|(_, _, distance)| distance)
+        .collect()
+}
+
+/// Characters the plot is drawn with
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlotStyle {
+    /// `x`, `-` and `|` borders with city numbers; safe on dumb terminals
+    Ascii,
+    /// Box-drawing borders (`┌ ─ ┐ │ └ ┘`) with `●` dots for cities
+    Unicode,
+}
+
+impl PlotStyle {
+    /// (top-left, top-right, bottom-left, bottom-right, horizontal, vertical)
+    fn border(self) -> (char, char, char, char, char, char) {
+        match self {
+            PlotStyle::Ascii => ('x', 'x', 'x', 'x', '-', '|'),
+            PlotStyle::Unicode => ('┌', '┐', '└', '┘', '─', '│'),
+        }
+    }
+
+    fn city(self, i: usize) -> String {
+        match self {
+            PlotStyle::Ascii => format!("{i:>2}"),
+            PlotStyle::Unicode => " ●".to_owned(),
+        }
+    }
+}
+
+/// Draws the cities on a `GRAPH_PIXELS` square grid. Labelled cities are named in a legend under
+/// the ASCII plot. With `SHOW_CAPTION` a summary line follows, including the tour `length` once
+/// one has been solved for.
+pub fn display_plot(points: &[Point], length: Option<f32>, style: PlotStyle) {
+    print!("{}", format_plot(points, length, style));
+}
+
+pub fn format_plot(points: &[Point], length: Option<f32>, style: PlotStyle) -> String {
+    let n = points.len();
+    let mut plot = empty_plot();
+    let square = map_square(points);
+
+    // points
+    for (i, point) in points.iter().enumerate() {
+        let (ix, iy) = to_pixel(point, square);
+        debug!("City {i}: ({:.2}, {:.2})        ({}, {}) ", point.x, point.y, ix, iy);
+        plot[ix][iy] = style.city(i);
+    }
+
+    let mut out = String::from("\nPlot:\n");
+    out.push_str(&render_plot(&plot, style));
+    if let (PlotStyle::Ascii, Some(legend)) = (style, format_legend(points)) {
+        out.push_str(&legend);
+        out.push('\n');
+    }
+
+    if SHOW_CAPTION {
+        out.push_str(&format_caption(n, length));
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Zooms into `[x_min, x_max] x [y_min, y_max]` of the map, using the whole plot for just that
+/// rectangle. Cities outside it are left out; city numbers still refer to the full instance.
+pub fn display_plot_region(points: &[Point], x_min: f64, x_max: f64, y_min: f64, y_max: f64, style: PlotStyle) {
+    print!("{}", format_plot_region(points, x_min, x_max, y_min, y_max, style));
+}
+
+pub fn format_plot_region(points: &[Point], x_min: f64, x_max: f64, y_min: f64, y_max: f64, style: PlotStyle) -> String {
+    let mut plot = empty_plot();
+    for (i, point) in points.iter().enumerate() {
+        if point.x < x_min || point.x > x_max || point.y < y_min || point.y > y_max {
+            continue;
+        }
+        // the far edges belong to the last cell rather than one past it
+        let scale = |v: f64, min: f64, max: f64| {
+            let width = if max > min { max - min } else { 1.0 };
+            (((v - min) / width * GRAPH_PIXELS as f64) as usize).min(GRAPH_PIXELS-1)
+        };
+        plot[scale(point.x, x_min, x_max)][scale(point.y, y_min, y_max)] = style.city(i);
+    }
+
+    let mut out = format!("\nPlot of ({x_min}, {y_min}) to ({x_max}, {y_max}):\n");
+    out.push_str(&render_plot(&plot, style));
+
+    out
+}
+
+/// Plots two tours over the same cities: edges only in `tour_a` as ` .`, only in `tour_b` as ` o`,
+/// and shared edges as ` #`. The edges that differ are listed underneath.
+pub fn display_tour_comparison(points: &[Point], tour_a: &[usize], tour_b: &[usize], style: PlotStyle) {
+    print!("{}", format_tour_comparison(points, tour_a, tour_b, style));
+}
+
+pub fn format_tour_comparison(points: &[Point], tour_a: &[usize], tour_b: &[usize], style: PlotStyle) -> String {
+    let mut plot = empty_plot();
+    let square = map_square(points);
+    let (only_a, only_b) = edge_difference(tour_a, tour_b);
+
+    for (a, b) in tour_edges(tour_a).into_iter().chain(tour_edges(tour_b)) {
+        let mark = if only_a.contains(&(a, b)) {
+            " ."
+        } else if only_b.contains(&(a, b)) {
+            " o"
+        } else {
+            " #"
+        };
+        draw_line(&mut plot, to_pixel(&points[a], square), to_pixel(&points[b], square), mark);
+    }
+    for (i, point) in points.iter().enumerate() {
+        let (ix, iy) = to_pixel(point, square);
+        plot[ix][iy] = style.city(i);
+    }
+
+    let format_edges = |edges: &Vec<Edge>| {
+        let edges: Vec<String> = edges.iter().map(|(a, b)| format!("{a}-{b}")).collect();
+        edges.join(", ")
+    };
+    let mut out = String::from("\nComparison (. first tour only, o second tour only, # both):\n");
+    out.push_str(&render_plot(&plot, style));
+    out.push_str(&format!("Only in first tour: {}\n", format_edges(&only_a)));
+    out.push_str(&format!("Only in second tour: {}\n", format_edges(&only_b)));
+
+    out
+}
+
+/// Every (i, j, grid[i][j]) with i != j, row by row
+fn off_diagonal(grid: &[Vec<f32>]) -> impl Iterator<Item = (usize, usize, f32)> + '_ {
+    grid.iter()
+        .enumerate()
+        .flat_map(|(i, row)| row.iter().enumerate().map(move |(j, &distance)| (i, j, distance)))
+        .filter(|&(i, j, _)| i != j)
+}
+
+fn empty_plot() -> Vec<Vec<String>> {
+    vec![vec!["  ".to_owned(); GRAPH_PIXELS]; GRAPH_PIXELS]
+}
+
+/// The square the plots cover, as its lower-left corner and side: `0..MAP_WIDTH` on both axes,
+/// grown to take in any city outside it, e.g. negative coordinates from data centred on the origin
+pub fn map_square(points: &[Point]) -> (f64, f64, f64) {
+    let x_min = points.iter().map(|p| p.x).fold(0.0, f64::min);
+    let y_min = points.iter().map(|p| p.y).fold(0.0, f64::min);
+    let x_max = points.iter().map(|p| p.x).fold(MAP_WIDTH as f64, f64::max);
+    let y_max = points.iter().map(|p| p.y).fold(MAP_WIDTH as f64, f64::max);
+
+    (x_min, y_min, (x_max - x_min).max(y_max - y_min))
+}
+
+/// Plot cell of a point in the `map_square` (x, y, side), the far edges in the last cell
+fn to_pixel(point: &Point, square: (f64, f64, f64)) -> (usize, usize) {
+    let (x_min, y_min, side) = square;
+    let scale_factor = side / GRAPH_PIXELS as f64;
+    let ix = (((point.x - x_min)/scale_factor) as usize).min(GRAPH_PIXELS-1);
+    let iy = (((point.y - y_min)/scale_factor) as usize).min(GRAPH_PIXELS-1);
+    (ix, iy)
+}
+
+/// Marks the cells along the segment between two plot cells, leaving cities and shared edges intact
+fn draw_line(plot: &mut [Vec<String>], from: (usize, usize), to: (usize, usize), mark: &str) {
+    let (dx, dy) = (to.0 as f64 - from.0 as f64, to.1 as f64 - from.1 as f64);
+    let steps = dx.abs().max(dy.abs()) as usize;
+    for step in 0..=steps {
+        let t = if steps == 0 { 0.0 } else { step as f64 / steps as f64 };
+        let ix = (from.0 as f64 + t * dx).round() as usize;
+        let iy = (from.1 as f64 + t * dy).round() as usize;
+        if plot[ix][iy] == "  " {
+            plot[ix][iy] = mark.to_owned();
+        }
+    }
+}
+
+/// Frames the plot with y increasing upwards; plot[x][y] holds each 2-character cell
+fn render_plot(plot: &[Vec<String>], style: PlotStyle) -> String {
+    let (top_left, top_right, bottom_left, bottom_right, horizontal, vertical) = style.border();
+    let rule: String = std::iter::repeat_n(horizontal, 2 * GRAPH_PIXELS).collect();
+
+    let mut out = format!("{top_left}{rule}{top_right}\n");
+    for j in 0..GRAPH_PIXELS {
+        out.push(vertical);
+        for column in plot {
+            out.push_str(&column[GRAPH_PIXELS-1-j]);
+        }
+        out.push(vertical);
+        out.push('\n');
+    }
+    out.push_str(&format!("{bottom_left}{rule}{bottom_right}\n"));
+
+    out
+}
+
+pub fn format_caption(n: usize, length: Option<f32>) -> String {
+    let mut caption = format!("{n} cities, {MAP_WIDTH} x {MAP_WIDTH} map");
+    if let Some(length) = length {
+        caption.push_str(&format!(", tour length {length:.1}"));
+    }
+
+    caption
+}
+
+pub fn format_path(path: &[usize]) -> String {
+    let pattern: Vec<String> = path.iter().map(|x| x.to_string()).collect();
+    let pattern = pattern.join(" > ");
+    format!("path: {pattern} > {}", path[0])
+}
+
+/// As `format_path`, naming each city by its label where `points` has one
+pub fn format_labeled_path(path: &[usize], points: &[Point]) -> String {
+    let pattern: Vec<String> = path.iter().map(|&x| city_name(points, x)).collect();
+    let pattern = pattern.join(" > ");
+    format!("path: {pattern} > {}", city_name(points, path[0]))
+}
+
+/// The city's label, or its index when it has none (or `points` doesn't reach it)
+pub fn city_name(points: &[Point], i: usize) -> String {
+    points.get(i).and_then(|p| p.label.clone()).unwrap_or_else(|| i.to_string())
+}
+
+/// `0 = Berlin, 1 = Paris, ...` for the labelled cities, as the plot only has room for numbers;
+/// `None` when no city has a label
+fn format_legend(points: &[Point]) -> Option<String> {
+    let names: Vec<String> = points
+        .iter()
+        .enumerate()
+        .filter_map(|(i, p)| p.label.as_ref().map(|label| format!("{i} = {label}")))
+        .collect();
+    if names.is_empty() { None } else { Some(names.join(", ")) }
+}
+
+/// Largest and mean absolute entry of a `grid_diff`, over the off-diagonal pairs
+pub fn format_diff_summary(diff: &[Vec<f32>]) -> String {
+    let n = diff.len();
+    let mut max = 0 as f32;
+    let mut sum = 0 as f32;
+    for (_, _, difference) in off_diagonal(diff) {
+        max = max.max(difference.abs());
+        sum += difference.abs();
+    }
+    let pairs = n * n.saturating_sub(1);
+    let mean = if pairs > 0 { sum / pairs as f32 } else { 0.0 };
+
+    format!("max |diff|: {max:.2}, mean |diff|: {mean:.2}")
+}
+
+/// Draws the tour's edges as ` .` over the cities, with the caption giving its `length`
+pub fn display_tour(points: &[Point], tour: &[usize], length: f32, style: PlotStyle) {
+    print!("{}", format_tour(points, tour, length, style));
+}
+
+pub fn format_tour(points: &[Point], tour: &[usize], length: f32, style: PlotStyle) -> String {
+    let mut plot = empty_plot();
+    let square = map_square(points);
+    for (a, b) in tour_edges(tour) {
+        draw_line(&mut plot, to_pixel(&points[a], square), to_pixel(&points[b], square), " .");
+    }
+    for (i, point) in points.iter().enumerate() {
+        let (ix, iy) = to_pixel(point, square);
+        plot[ix][iy] = style.city(i);
+    }
+
+    let mut out = String::from("\nTour:\n");
+    out.push_str(&render_plot(&plot, style));
+    out.push_str(&format_caption(points.len(), Some(length)));
+    out.push('\n');
+
+    out
+}
+
+/// Turn-by-turn route for a dispatcher, one numbered leg per line including the return to the start:
+/// `1. Go from city 0 (10, 20) to city 3 (40, 15), distance 31.6`, with labels in place of numbers
+pub fn format_directions(points: &[Point], tour: &[usize]) -> String {
+    let n = tour.len();
+    let mut out = String::new();
+    if n < 2 {
+        return out;
+    }
+
+    for i in 0..n {
+        let (a, b) = (tour[i], tour[(i + 1) % n]);
+        out.push_str(&format!(
+            "{}. Go from city {} {} to city {} {}, distance {:.1}\n",
+            i + 1, city_name(points, a), format_coords(&points[a]), city_name(points, b), format_coords(&points[b]),
+            points[a].distance(&points[b])
+        ));
+    }
+
+    out
+}
+
+/// `(x, y)` rounded to 2 decimal places, with whole numbers printed without a fraction
+fn format_coords(point: &Point) -> String {
+    let round = |v: f64| (v * 100.0).round() / 100.0;
+    format!("({}, {})", round(point.x), round(point.y))
+}
+
+/// Redraws a tour in place on stdout as a search improves it, pausing between frames so each can
+/// be seen. It draws nothing unless stdout is a terminal, so piped output only gets the result.
+pub struct Animation {
+    delay: Duration,
+    enabled: bool,
+}
+
+impl Animation {
+    pub fn new(delay: Duration) -> Self {
+        Animation { delay, enabled: io::stdout().is_terminal() }
+    }
+
+    /// Clears the screen and draws `tour` as `display_tour` does
+    pub fn frame(&self, points: &[Point], tour: &[usize], length: f32, style: PlotStyle) {
+        if self.enabled {
+            print!("\x1b[2J\x1b[H{}", format_tour(points, tour, length, style));
+            std::thread::sleep(self.delay);
+        }
+    }
+}
+
+/// Width in characters of the filled part of a full progress bar
+const PROGRESS_WIDTH: usize = 30;
+
+/// Progress bar redrawn in place on stderr for long searches. It draws nothing unless stderr
+/// is a terminal, so piped or redirected output stays clean.
+pub struct ProgressBar {
+    start: Instant,
+    enabled: bool,
+}
+
+impl Default for ProgressBar {
+    fn default() -> Self {
+        ProgressBar { start: Instant::now(), enabled: io::stderr().is_terminal() }
+    }
+}
+
+impl ProgressBar {
+    /// Starts the clock the ETA is worked out from
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&self, fraction: f64) {
+        if self.enabled {
+            eprint!("\r{}", format_progress_bar(fraction, self.start.elapsed()));
+        }
+    }
+
+    /// Clears the bar's line, ready for the result
+    pub fn finish(&self) {
+        if self.enabled {
+            eprint!("\r\x1b[2K");
+        }
+    }
+}
+
+/// `[###########...................]  36.7%  ETA 4.2s`; the ETA is left out until some of the
+/// work is done
+pub fn format_progress_bar(fraction: f64, elapsed: Duration) -> String {
+    let fraction = fraction.clamp(0.0, 1.0);
+    let filled = (fraction * PROGRESS_WIDTH as f64).round() as usize;
+    let mut bar = format!("[{}{}] {:>5.1}%", "#".repeat(filled), ".".repeat(PROGRESS_WIDTH - filled), fraction * 100.0);
+    if let Some(remaining) = estimate_remaining(elapsed, fraction) {
+        bar.push_str(&format!("  ETA {remaining:.1?}"));
+    }
+
+    bar
+}
+
+/// Time still to go if the rest of the work runs at the pace so far: `elapsed * (1 - f) / f`.
+/// `None` before anything is done.
+pub fn estimate_remaining(elapsed: Duration, fraction: f64) -> Option<Duration> {
+    if fraction <= 0.0 || !fraction.is_finite() {
+        return None;
+    }
+
+    Some(elapsed.mul_f64((1.0 - fraction.min(1.0)) / fraction))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::brute_force::brute_force;
+    use crate::grid::calculate_edge_grid;
+    use crate::input::read_points;
+    use crate::output::write_svg;
+    use crate::point::generate_points_with;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn tour_plot_draws_each_edge() {
+        let corners = [(10.0, 10.0), (90.0, 10.0), (90.0, 90.0), (10.0, 90.0)];
+        let points: Vec<Point> = corners.iter().map(|&(x, y)| Point { x, y, label: None }).collect();
+
+        let square = format_tour(&points, &[0, 1, 2, 3], 320.0, PlotStyle::Ascii);
+        let bowtie = format_tour(&points, &[0, 2, 1, 3], 386.3, PlotStyle::Ascii);
+        assert!(square.contains(" . . ."));
+        assert!(square.ends_with(&format!("{}\n", format_caption(4, Some(320.0)))));
+        // the bowtie's diagonals cross in the middle of the map, which the square leaves empty
+        let centre = |plot: &str| {
+            let row = plot.lines().nth(3 + GRAPH_PIXELS / 2 - 1).unwrap();
+            row[1 + GRAPH_PIXELS..3 + GRAPH_PIXELS].to_owned()
+        };
+        assert_eq!(centre(&bowtie), " .");
+        assert_eq!(centre(&square), "  ");
+    }
+
+    #[test]
+    fn comparison_marks_the_edges_a_two_opt_move_swaps() {
+        let corners = [(10.0, 10.0), (90.0, 10.0), (90.0, 90.0), (10.0, 90.0), (50.0, 95.0)];
+        let points: Vec<Point> = corners.iter().map(|&(x, y)| Point { x, y, label: None }).collect();
+        let before = [0, 1, 2, 4, 3];
+        // reversing 2..=4 swaps 1-2 and 3-0 for 1-3 and 2-0
+        let after = [0, 1, 3, 4, 2];
+
+        let plot = format_tour_comparison(&points, &before, &after, PlotStyle::Ascii);
+        assert!(plot.contains(" .") && plot.contains(" o") && plot.contains(" #"));
+        assert!(plot.contains("Only in first tour: 1-2, 0-3\n"));
+        assert!(plot.contains("Only in second tour: 1-3, 0-2\n"));
+    }
+
+    #[test]
+    fn unicode_style_draws_box_borders() {
+        let points = vec![Point { x: 20.0, y: 30.0, label: None }, Point { x: 70.0, y: 60.0, label: None }];
+
+        let unicode = format_plot(&points, None, PlotStyle::Unicode);
+        for border in ['┌', '┐', '└', '┘', '─', '│', '●'] {
+            assert!(unicode.contains(border), "{border}");
+        }
+        let ascii = format_plot(&points, None, PlotStyle::Ascii);
+        assert!(ascii.is_ascii());
+        assert!(ascii.contains(" 0") && ascii.contains(" 1"));
+    }
+
+    #[test]
+    fn one_direction_per_leg() {
+        let points = vec![
+            Point { x: 0.0, y: 0.0, label: None },
+            Point { x: 3.0, y: 4.0, label: None },
+            Point { x: 3.0, y: 0.0, label: None },
+        ];
+
+        let directions = format_directions(&points, &[0, 1, 2]);
+        assert_eq!(directions.lines().count(), 3);
+        assert_eq!(directions.lines().next(), Some("1. Go from city 0 (0, 0) to city 1 (3, 4), distance 5.0"));
+        assert_eq!(directions.lines().last(), Some("3. Go from city 2 (3, 0) to city 0 (0, 0), distance 3.0"));
+    }
+
+    #[test]
+    fn region_plot_only_shows_cities_inside_it() {
+        let coordinates = [(10.0, 10.0), (12.0, 12.0), (80.0, 80.0), (11.0, 15.0)];
+        let points: Vec<Point> = coordinates.iter().map(|&(x, y)| Point { x, y, label: None }).collect();
+
+        let zoomed = format_plot_region(&points, 0.0, 20.0, 0.0, 20.0, PlotStyle::Ascii);
+        assert!(zoomed.starts_with("\nPlot of (0, 0) to (20, 20):\n"));
+        let plot: String = zoomed.lines().skip(2).collect();
+        for city in [" 0", " 1", " 3"] {
+            assert_eq!(plot.matches(city).count(), 1, "{city}");
+        }
+        assert!(!plot.contains(" 2"));
+    }
+
+    #[test]
+    fn histogram_counts_every_pair_once() {
+        let points = generate_points_with(12, 100, false, &mut StdRng::seed_from_u64(4)).unwrap();
+        let grid = calculate_edge_grid(points).unwrap();
+        assert_eq!(edge_lengths(&grid).len(), 12 * 11 / 2);
+
+        let histogram = format_histogram(&edge_lengths(&grid), 5);
+        assert_eq!(histogram.lines().count(), 5);
+        let counts = histogram.lines().map(|line| line.rsplit(' ').next().unwrap().parse::<usize>().unwrap());
+        assert_eq!(counts.sum::<usize>(), 12 * 11 / 2);
+
+        let single = format!("     1.0 -      2.0 | {} 3\n", "#".repeat(HISTOGRAM_WIDTH));
+        assert_eq!(format_histogram(&[1.0, 2.0, 2.0], 1), single);
+    }
+
+    #[test]
+    fn progress_bar_fills_and_projects_the_pace() {
+        assert_eq!(estimate_remaining(Duration::from_secs(2), 0.25), Some(Duration::from_secs(6)));
+        assert_eq!(estimate_remaining(Duration::from_secs(2), 1.0), Some(Duration::ZERO));
+        assert_eq!(estimate_remaining(Duration::from_secs(2), 0.0), None);
+
+        let quarter = format!("[{}{}]  25.0%  ETA 6.0s", "#".repeat(8), ".".repeat(22));
+        assert_eq!(format_progress_bar(0.25, Duration::from_secs(2)), quarter);
+        let empty = format!("[{}]   0.0%", ".".repeat(PROGRESS_WIDTH));
+        assert_eq!(format_progress_bar(0.0, Duration::from_secs(2)), empty);
+        let full = format!("[{}] 100.0%", "#".repeat(PROGRESS_WIDTH));
+        assert!(format_progress_bar(1.5, Duration::from_secs(2)).starts_with(&full));
+    }
+
+    #[test]
+    fn negative_coordinates_widen_the_map() {
+        let points = read_points("-50 -20\n30 -45.5\n10 40\n-25,35\n".as_bytes()).unwrap();
+        assert_eq!(map_square(&points), (-50.0, -45.5, 150.0));
+        assert_eq!(map_square(&[]), (0.0, 0.0, MAP_WIDTH as f64));
+
+        let plot = format_plot(&points, None, PlotStyle::Ascii);
+        for city in [" 0", " 1", " 2", " 3"] {
+            assert!(plot.contains(city), "{city} missing");
+        }
+        let (tour, length) = brute_force(&calculate_edge_grid(points.clone()).unwrap()).unwrap();
+        assert_eq!(tour.len(), 4);
+        assert!(length > 0.0);
+
+        let mut svg = Vec::new();
+        write_svg(&mut svg, &points, &tour, (300.0, 300.0), 1.0, 3.0).unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+        let centres = svg.lines().filter(|line| line.contains("<circle")).flat_map(|line| {
+            let fields: Vec<&str> = line.split('"').collect();
+            [fields[1].parse::<f64>().unwrap(), fields[3].parse::<f64>().unwrap()]
+        });
+        assert!(centres.into_iter().all(|c| (0.0..=300.0).contains(&c)));
+    }
+}
@@ -0,0 +1,40 @@
+use std::fmt::{Debug, Display};
+use std::ops::Add;
+
+/// Scalar a distance grid can hold. `f32` is what the grids are built with; `u64` gives exact
+/// sums, so equal tours always compare equal and ties resolve the same way on every run.
+pub trait Distance: Copy + PartialOrd + Add<Output = Self> + Debug + Display {
+    const ZERO: Self;
+    /// Stands in for "no tour yet"; no real tour should cost this much
+    const MAX: Self;
+
+    /// Whether two tour lengths count as equal, e.g. for a tie-break between them
+    fn ties(self, other: Self) -> bool;
+}
+
+/// Relative difference below which two float lengths tie, as sums of the same edges in another
+/// order rarely match exactly
+const TIE_TOLERANCE: f32 = 1e-5;
+
+impl Distance for f32 {
+    const ZERO: Self = 0.0;
+    const MAX: Self = f32::MAX;
+
+    fn ties(self, other: Self) -> bool {
+        (self - other).abs() <= other.abs() * TIE_TOLERANCE
+    }
+}
+
+impl Distance for u64 {
+    const ZERO: Self = 0;
+    const MAX: Self = u64::MAX;
+
+    fn ties(self, other: Self) -> bool {
+        self == other
+    }
+}
+
+/// Rounds every distance to the nearest whole number, as TSPLIB does for its integer instances
+pub fn round_grid(grid: &[Vec<f32>]) -> Vec<Vec<u64>> {
+    grid.iter().map(|row| row.iter().map(|d| d.round().max(0.0) as u64).collect()).collect()
+}
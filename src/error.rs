@@ -0,0 +1,88 @@
+use std::fmt;
+
+/// Everything that can go wrong when handing input to the solvers
+#[derive(Debug)]
+pub enum TspError {
+    /// No cities, or nothing to build them from
+    EmptyInput,
+    /// The distance grid has a row whose length differs from the number of rows
+    NonSquareGrid,
+    /// A tour that isn't a permutation of the cities, with the reason
+    InvalidTour(String),
+    /// Two inputs that must describe the same cities have different sizes (expected, found)
+    SizeMismatch(usize, usize),
+    /// No tour satisfies the constraints
+    Infeasible,
+    /// More cities than the solver can represent (limit, found)
+    TooManyCities(usize, usize),
+    /// A solver setting outside the range it accepts, with the reason
+    InvalidConfig(String),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for TspError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TspError::EmptyInput => write!(f, "no cities given"),
+            TspError::NonSquareGrid => write!(f, "distance grid is not square"),
+            TspError::InvalidTour(reason) => write!(f, "invalid tour: {reason}"),
+            TspError::SizeMismatch(expected, found) => write!(f, "expected {expected} cities, found {found}"),
+            TspError::Infeasible => write!(f, "no feasible tour exists"),
+            TspError::TooManyCities(limit, found) => write!(f, "{found} cities is more than the {limit} this solver supports"),
+            TspError::InvalidConfig(reason) => write!(f, "invalid settings: {reason}"),
+            TspError::Io(e) => write!(f, "i/o error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for TspError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TspError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for TspError {
+    fn from(e: std::io::Error) -> Self {
+        TspError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::brute_force::brute_force;
+    use crate::checkpoint::Checkpoint;
+    use crate::genetic::{genetic, GeneticConfig};
+    use crate::grid::{calculate_edge_grid, grid_diff};
+    use crate::point::Point;
+    use crate::tour::tour_length;
+
+    fn line(n: usize) -> Vec<Vec<f32>> {
+        calculate_edge_grid((0..n).map(|i| Point { x: i as f64, y: 0.0, label: None }).collect()).unwrap()
+    }
+
+    #[test]
+    fn bad_input_gives_the_matching_variant() {
+        assert!(matches!(calculate_edge_grid(Vec::new()), Err(TspError::EmptyInput)));
+        assert!(matches!(brute_force(&[vec![0.0, 1.0], vec![1.0]]), Err(TspError::NonSquareGrid)));
+        assert!(matches!(tour_length(&line(3), &[0, 1, 1]), Err(TspError::InvalidTour(_))));
+        assert!(matches!(grid_diff(&line(3), &line(4)), Err(TspError::SizeMismatch(3, 4))));
+        let unreachable = [vec![0.0, f32::INFINITY], vec![f32::INFINITY, 0.0]];
+        assert!(matches!(brute_force(&unreachable), Err(TspError::Infeasible)));
+        assert!(matches!(brute_force(&line(65)), Err(TspError::TooManyCities(64, 65))));
+        let config = GeneticConfig { population: 0, ..GeneticConfig::default() };
+        assert!(matches!(genetic(&line(3), &config), Err(TspError::InvalidConfig(_))));
+        let missing = std::env::temp_dir().join("tsp-no-such-checkpoint.json");
+        assert!(matches!(Checkpoint::load(&missing), Err(TspError::Io(_))));
+    }
+
+    #[test]
+    fn messages_name_the_problem() {
+        assert_eq!(TspError::SizeMismatch(3, 4).to_string(), "expected 3 cities, found 4");
+        assert_eq!(TspError::TooManyCities(64, 65).to_string(), "65 cities is more than the 64 this solver supports");
+        assert_eq!(TspError::InvalidTour("city 1 twice".to_owned()).to_string(), "invalid tour: city 1 twice");
+    }
+}
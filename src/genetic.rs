@@ -0,0 +1,296 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+
+use crate::bound::one_tree_bound;
+use crate::error::TspError;
+use crate::grid::validate_grid;
+use crate::tour::{random_tour, tour_length};
+
+/// Settings for `genetic`
+#[derive(Clone, Debug, PartialEq)]
+pub struct GeneticConfig {
+    pub population: usize,
+    pub generations: usize,
+    /// Chance that a child has a random segment of its tour reversed
+    pub mutation_rate: f64,
+    /// Tours compared when picking each parent; the shortest wins
+    pub tournament: usize,
+    pub seed: u64,
+    /// Stop once the best tour is within this fraction of `one_tree_bound`, e.g. 0.05 for 5%,
+    /// instead of always running every generation
+    pub target_gap: Option<f32>,
+}
+
+impl GeneticConfig {
+    /// Checks every setting is one `genetic` can run with
+    pub fn validate(&self) -> Result<(), TspError> {
+        if self.population == 0 {
+            return Err(TspError::InvalidConfig("the population must have at least one tour".to_owned()));
+        }
+        if self.tournament == 0 {
+            return Err(TspError::InvalidConfig("a tournament needs at least one entrant".to_owned()));
+        }
+        if !(0.0..=1.0).contains(&self.mutation_rate) {
+            return Err(TspError::InvalidConfig(format!("mutation rate {} is not between 0 and 1", self.mutation_rate)));
+        }
+        if let Some(gap) = self.target_gap.filter(|gap| gap.is_nan() || *gap < 0.0) {
+            return Err(TspError::InvalidConfig(format!("target gap {gap} is not zero or more")));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for GeneticConfig {
+    fn default() -> Self {
+        GeneticConfig {
+            population: 100,
+            generations: 200,
+            mutation_rate: 0.2,
+            tournament: 3,
+            seed: 0,
+            target_gap: None,
+        }
+    }
+}
+
+/// Genetic algorithm: tournament selection, order crossover and reversal mutation, keeping the
+/// best tour of each generation.
+///
+/// Children are bred and scored in parallel, each with its own RNG seeded from `config.seed`, the
+/// generation and its index, never a shared one. So the result depends only on the seed, not on
+/// how many threads there are or how rayon schedules them.
+pub fn genetic(grid: &[Vec<f32>], config: &GeneticConfig) -> Result<(Vec<usize>, f32), TspError> {
+    let mut improvements = genetic_improvements(grid, config)?;
+    for improvement in improvements.by_ref() {
+        improvement?;
+    }
+
+    Ok(improvements.best)
+}
+
+/// The same run as `genetic`, one generation at a time as it is iterated: yields the best tour of
+/// the first generation, then each later one that is strictly shorter, so the lengths decrease and
+/// the last item is what `genetic` returns. With a `target_gap` the run ends early, after the first
+/// tour close enough to the lower bound.
+pub fn genetic_improvements<'a>(grid: &'a [Vec<f32>], config: &'a GeneticConfig) -> Result<Improvements<'a>, TspError> {
+    validate_grid(grid)?;
+    config.validate()?;
+    let n = grid.len();
+
+    let population: Vec<(Vec<usize>, f32)> = (0..config.population)
+        .into_par_iter()
+        .map(|i| {
+            let tour = random_tour(n, &mut individual_rng(config.seed, 0, i));
+            let length = tour_length(grid, &tour)?;
+            Ok((tour, length))
+        })
+        .collect::<Result<_, TspError>>()?;
+    let best = fittest(&population).clone();
+    let bound = config.target_gap.map(|_| one_tree_bound(grid));
+
+    Ok(Improvements { grid, config, population, generation: 0, best, bound, unreported: true })
+}
+
+/// Iterator from `genetic_improvements`
+pub struct Improvements<'a> {
+    grid: &'a [Vec<f32>],
+    config: &'a GeneticConfig,
+    population: Vec<(Vec<usize>, f32)>,
+    generation: usize,
+    best: (Vec<usize>, f32),
+    /// `one_tree_bound` of the grid, when there's a target gap to measure against it
+    bound: Option<f32>,
+    /// `best` hasn't been yielded yet
+    unreported: bool,
+}
+
+impl Improvements<'_> {
+    /// Generations bred so far, not counting the random first one
+    pub fn generations(&self) -> usize {
+        self.generation
+    }
+
+    fn reached_target(&self) -> bool {
+        match (self.config.target_gap, self.bound) {
+            (Some(gap), Some(bound)) => self.best.1 - bound <= gap * bound,
+            _ => false,
+        }
+    }
+
+    /// Replaces the population with the next generation's
+    fn breed(&mut self) -> Result<(), TspError> {
+        let (grid, config, generation) = (self.grid, self.config, self.generation);
+        let n = grid.len();
+        let elite = fittest(&self.population).clone();
+        let parents = &self.population;
+        let mut children: Vec<(Vec<usize>, f32)> = (1..config.population)
+            .into_par_iter()
+            .map(|i| {
+                let mut rng = individual_rng(config.seed, generation, i);
+                let a = &select(parents, config.tournament, &mut rng).0;
+                let b = &select(parents, config.tournament, &mut rng).0;
+                let mut child = order_crossover(a, b, &mut rng);
+                if n > 2 && rng.gen_bool(config.mutation_rate) {
+                    let (x, y) = (rng.gen_range(1..n), rng.gen_range(1..n));
+                    child[x.min(y)..=x.max(y)].reverse();
+                }
+                let length = tour_length(grid, &child)?;
+                Ok((child, length))
+            })
+            .collect::<Result<_, TspError>>()?;
+        children.push(elite);
+        self.population = children;
+
+        Ok(())
+    }
+}
+
+impl Iterator for Improvements<'_> {
+    type Item = Result<(Vec<usize>, f32), TspError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.unreported {
+                self.unreported = false;
+                return Some(Ok(self.best.clone()));
+            }
+            if self.generation >= self.config.generations || self.reached_target() {
+                return None;
+            }
+
+            self.generation += 1;
+            if let Err(e) = self.breed() {
+                self.generation = self.config.generations;
+                return Some(Err(e));
+            }
+            let fittest = fittest(&self.population);
+            if fittest.1 < self.best.1 {
+                self.best = fittest.clone();
+                self.unreported = true;
+            }
+        }
+    }
+}
+
+/// RNG for one individual of one generation: a SplitMix64 hash of the three, so neighbouring
+/// indices still get unrelated streams
+fn individual_rng(seed: u64, generation: usize, index: usize) -> StdRng {
+    let mut z = seed ^ (generation as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ (index as u64).wrapping_mul(0xD1B5_4A32_D192_ED03);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    StdRng::seed_from_u64(z ^ (z >> 31))
+}
+
+/// Shortest tour, the earliest on ties
+fn fittest(population: &[(Vec<usize>, f32)]) -> &(Vec<usize>, f32) {
+    population.iter().reduce(|best, candidate| if candidate.1 < best.1 { candidate } else { best }).unwrap()
+}
+
+fn select<'a, R: Rng>(population: &'a [(Vec<usize>, f32)], tournament: usize, rng: &mut R) -> &'a (Vec<usize>, f32) {
+    let entrants: Vec<&(Vec<usize>, f32)> = (0..tournament).map(|_| &population[rng.gen_range(0..population.len())]).collect();
+    entrants.into_iter().reduce(|best, candidate| if candidate.1 < best.1 { candidate } else { best }).unwrap()
+}
+
+/// Copies a random slice of `a` into the child, then fills the rest with the missing cities in the
+/// order they appear in `b`. City 0 stays first, as in both parents.
+fn order_crossover<R: Rng>(a: &[usize], b: &[usize], rng: &mut R) -> Vec<usize> {
+    let n = a.len();
+    if n < 3 {
+        return a.to_vec();
+    }
+
+    let (x, y) = (rng.gen_range(1..n), rng.gen_range(1..n));
+    let (start, end) = (x.min(y), x.max(y));
+    let mut taken = vec![false; n];
+    taken[0] = true;
+    for &city in &a[start..=end] {
+        taken[city] = true;
+    }
+
+    let mut rest = b.iter().copied().filter(|&city| !taken[city]);
+    (0..n)
+        .map(|i| match i {
+            0 => 0,
+            i if (start..=end).contains(&i) => a[i],
+            _ => rest.next().unwrap(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::calculate_edge_grid;
+    use crate::point::generate_points_with;
+    use crate::tour::validate_tour;
+
+    fn instance(n: usize, seed: u64) -> Vec<Vec<f32>> {
+        calculate_edge_grid(generate_points_with(n, 100, false, &mut StdRng::seed_from_u64(seed)).unwrap()).unwrap()
+    }
+
+    fn with_threads<T: Send>(threads: usize, run: impl FnOnce() -> T + Send) -> T {
+        rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap().install(run)
+    }
+
+    #[test]
+    fn same_seed_same_tour_on_any_thread_count() {
+        let grid = instance(15, 4);
+        let config = GeneticConfig { generations: 30, seed: 11, ..GeneticConfig::default() };
+
+        let single = with_threads(1, || genetic(&grid, &config).unwrap());
+        let several = with_threads(4, || genetic(&grid, &config).unwrap());
+        assert_eq!(single, several);
+        validate_tour(&single.0, 15).unwrap();
+        assert_eq!(single.0[0], 0);
+        assert_eq!(tour_length(&grid, &single.0).unwrap(), single.1);
+    }
+
+    #[test]
+    fn improvements_shrink_to_the_final_tour() {
+        let grid = instance(20, 2);
+        let config = GeneticConfig { generations: 60, seed: 5, ..GeneticConfig::default() };
+
+        let improvements: Vec<(Vec<usize>, f32)> =
+            genetic_improvements(&grid, &config).unwrap().collect::<Result<_, _>>().unwrap();
+        assert!(improvements.len() > 1);
+        assert!(improvements.windows(2).all(|pair| pair[1].1 < pair[0].1));
+        assert_eq!(improvements.last(), Some(&genetic(&grid, &config).unwrap()));
+    }
+
+    #[test]
+    fn generous_target_gap_stops_early() {
+        let grid = instance(20, 2);
+        let config = GeneticConfig { generations: 500, target_gap: Some(0.5), ..GeneticConfig::default() };
+
+        let mut improvements = genetic_improvements(&grid, &config).unwrap();
+        let (_, length) = improvements.by_ref().last().unwrap().unwrap();
+        assert!(improvements.generations() < 50, "ran {} generations", improvements.generations());
+        assert!(length <= 1.5 * one_tree_bound(&grid));
+        assert_eq!(genetic(&grid, &config).unwrap().1, length);
+    }
+
+    #[test]
+    fn out_of_range_settings_are_errors() {
+        let grid = instance(6, 0);
+        for config in [
+            GeneticConfig { mutation_rate: 1.5, ..GeneticConfig::default() },
+            GeneticConfig { mutation_rate: -0.1, ..GeneticConfig::default() },
+            GeneticConfig { mutation_rate: f64::NAN, ..GeneticConfig::default() },
+            GeneticConfig { population: 0, ..GeneticConfig::default() },
+            GeneticConfig { tournament: 0, ..GeneticConfig::default() },
+            GeneticConfig { target_gap: Some(-0.1), ..GeneticConfig::default() },
+            GeneticConfig { target_gap: Some(f32::NAN), ..GeneticConfig::default() },
+        ] {
+            assert!(matches!(genetic(&grid, &config), Err(TspError::InvalidConfig(_))), "{config:?}");
+        }
+    }
+
+    #[test]
+    fn bad_grids_are_errors() {
+        let config = GeneticConfig::default();
+        assert!(matches!(genetic(&Vec::new(), &config), Err(TspError::EmptyInput)));
+        assert!(matches!(genetic(&[vec![0.0, 1.0], vec![1.0]], &config), Err(TspError::NonSquareGrid)));
+    }
+}
@@ -0,0 +1,173 @@
+use crate::point::Point;
+
+/// Cross product of (a -> b) and (a -> c): positive when c is to the left of a -> b
+fn cross(a: &Point, b: &Point, c: &Point) -> f64 {
+    let (u, v) = (b - a, c - a);
+    u.x * v.y - u.y * v.x
+}
+
+/// Whether segments a1-a2 and b1-b2 cross at a single point inside both. Segments that only touch,
+/// e.g. consecutive tour edges sharing a city, or that overlap along a line don't count.
+pub fn edges_cross(a1: &Point, a2: &Point, b1: &Point, b2: &Point) -> bool {
+    let (d1, d2) = (cross(b1, b2, a1), cross(b1, b2, a2));
+    let (d3, d4) = (cross(a1, a2, b1), cross(a1, a2, b2));
+    d1 * d2 < 0.0 && d3 * d4 < 0.0
+}
+
+/// Pairs of tour positions `(i, j)`, i < j, whose edges `tour[i] -> tour[i+1]` and
+/// `tour[j] -> tour[j+1]` (wrapping around) cross. An optimal Euclidean tour has none.
+pub fn tour_crossings(points: &[Point], tour: &[usize]) -> Vec<(usize, usize)> {
+    let n = tour.len();
+    let mut crossings = Vec::new();
+    for i in 0..n {
+        for j in i+2..n {
+            let (a1, a2) = (&points[tour[i]], &points[tour[(i + 1) % n]]);
+            let (b1, b2) = (&points[tour[j]], &points[tour[(j + 1) % n]]);
+            if edges_cross(a1, a2, b1, b2) {
+                crossings.push((i, j));
+            }
+        }
+    }
+
+    crossings
+}
+
+/// Indices of the convex hull's vertices in counter-clockwise order (Andrew's monotone chain).
+/// Points lying on a hull edge rather than at a corner are left out.
+pub fn convex_hull(points: &[Point]) -> Vec<usize> {
+    let n = points.len();
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&a, &b| {
+        points[a].x.total_cmp(&points[b].x).then(points[a].y.total_cmp(&points[b].y))
+    });
+    order.dedup_by(|a, b| points[*a] == points[*b]);
+    if order.len() < 3 {
+        return order;
+    }
+
+    let mut hull: Vec<usize> = Vec::with_capacity(2 * order.len());
+    // lower hull, then upper hull
+    for pass in 0..2 {
+        let floor = hull.len();
+        for &i in order.iter() {
+            while hull.len() >= floor + 2 && cross(&points[hull[hull.len()-2]], &points[hull[hull.len()-1]], &points[i]) <= 0.0 {
+                hull.pop();
+            }
+            hull.push(i);
+        }
+        hull.pop();
+        if pass == 0 {
+            order.reverse();
+        }
+    }
+
+    hull
+}
+
+/// True when every city is a corner of the convex hull, in which case visiting them in hull order
+/// is an optimal tour
+pub fn is_convex_position(points: &[Point]) -> bool {
+    convex_hull(points).len() == points.len()
+}
+
+/// Maximal runs of three or more cities on a common line, each ordered along the line so the
+/// outer two cities come first and last
+pub fn collinear_groups(points: &[Point]) -> Vec<Vec<usize>> {
+    let n = points.len();
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+
+    for i in 0..n {
+        for j in i+1..n {
+            let (dx, dy) = (points[j].x - points[i].x, points[j].y - points[i].y);
+            let length_sq = dx * dx + dy * dy;
+            if length_sq == 0.0 {
+                continue;
+            }
+
+            let mut line: Vec<usize> = (0..n)
+                .filter(|&k| cross(&points[i], &points[j], &points[k]).abs() <= 1e-9 * length_sq)
+                .collect();
+            // too short, or the same line was already found from an earlier pair
+            if line.len() < 3 || groups.iter().any(|g| g.len() == line.len() && line.iter().all(|k| g.contains(k))) {
+                continue;
+            }
+
+            let along = |k: usize| (points[k].x - points[i].x) * dx + (points[k].y - points[i].y) * dy;
+            line.sort_by(|&a, &b| along(a).total_cmp(&along(b)));
+            groups.push(line);
+        }
+    }
+
+    groups
+}
+
+/// Times the closed tour switches between moving along x and along y when every leg is walked
+/// Manhattan-style, first along x and then along y, e.g. a picker changing between aisles and
+/// cross-aisles. A leg needing both axes switches once itself; zero-length legs are skipped.
+pub fn tour_axis_changes(points: &[Point], tour: &[usize]) -> usize {
+    let n = tour.len();
+    let mut axes = Vec::with_capacity(2 * n);
+    for i in 0..n {
+        let (a, b) = (&points[tour[i]], &points[tour[(i + 1) % n]]);
+        if b.x != a.x {
+            axes.push('x');
+        }
+        if b.y != a.y {
+            axes.push('y');
+        }
+    }
+
+    let m = axes.len();
+    (0..m).filter(|&i| m > 1 && axes[i] != axes[(i + 1) % m]).count()
+}
+
+/// Degrees turned at each city of the closed tour, `angles[i]` being the turn at `tour[i]` (so
+/// `angles[0]` is where the return leg meets the first leg). 0 is straight on and 180 a U-turn;
+/// left and right turns count the same. A leg of zero length has no direction, so gives 0.
+pub fn tour_turn_angles(points: &[Point], tour: &[usize]) -> Vec<f32> {
+    let n = tour.len();
+    if n < 3 {
+        return vec![0.0; n];
+    }
+
+    (0..n)
+        .map(|i| {
+            let prev = &points[tour[(i + n - 1) % n]];
+            let here = &points[tour[i]];
+            let next = &points[tour[(i + 1) % n]];
+            let (Point { x: ax, y: ay, .. }, Point { x: bx, y: by, .. }) = (here - prev, next - here);
+            if (ax == 0.0 && ay == 0.0) || (bx == 0.0 && by == 0.0) {
+                return 0.0;
+            }
+            (ax * by - ay * bx).atan2(ax * bx + ay * by).abs().to_degrees() as f32
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(coordinates: &[(f64, f64)]) -> Vec<Point> {
+        coordinates.iter().map(|&(x, y)| Point { x, y, label: None }).collect()
+    }
+
+    #[test]
+    fn three_on_a_line_and_one_off_it() {
+        // listed out of order along the line; the outer two come first and last
+        let cities = points(&[(2.0, 2.0), (0.0, 0.0), (5.0, 1.0), (4.0, 4.0)]);
+        let groups = collinear_groups(&cities);
+        assert_eq!(groups.len(), 1);
+        assert!(groups[0] == [1, 0, 3] || groups[0] == [3, 0, 1], "{groups:?}");
+        assert!(collinear_groups(&points(&[(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)])).is_empty());
+    }
+
+    #[test]
+    fn square_turns_a_right_angle_at_every_corner() {
+        let square = points(&[(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0), (1.0, 0.0)]);
+        assert_eq!(tour_turn_angles(&square, &[0, 1, 2, 3]), vec![90.0; 4]);
+        // a city halfway along an edge is passed straight through
+        assert_eq!(tour_turn_angles(&square, &[0, 4, 1, 2, 3]), vec![90.0, 0.0, 90.0, 90.0, 90.0]);
+        assert_eq!(tour_turn_angles(&square, &[0, 1, 4]), vec![180.0, 180.0, 0.0]);
+    }
+}
@@ -0,0 +1,323 @@
+use log::debug;
+use rayon::prelude::*;
+use std::time::Instant;
+
+use crate::display::format_grid;
+use crate::error::TspError;
+use crate::point::Point;
+use crate::MEASURE_TIMING;
+
+/// Slack, relative to the detour length, before a triangle counts as broken
+const TRIANGLE_TOLERANCE: f32 = 1e-5;
+
+/// Calculate distances between points, as a grid, where grid[i][j] is the ditsance from city i to city j
+pub fn calculate_edge_grid(points: Vec<Point>) -> Result<Vec<Vec<f32>>, TspError> {
+    if points.is_empty() {
+        return Err(TspError::EmptyInput);
+    }
+
+    Ok(calculate_edge_grid_with(&points, Point::distance))
+}
+
+/// Builds the grid with a caller-supplied metric, where grid[i][j] = f(points[i], points[j]).
+/// The diagonal is left at 0 and `f` is called for both directions, so asymmetric costs are fine.
+///
+/// Rows are filled in parallel; each row is computed in full rather than mirrored, so no two threads
+/// ever write to the same row.
+pub fn calculate_edge_grid_with<F: Fn(&Point, &Point) -> f32 + Sync>(points: &[Point], f: F) -> Vec<Vec<f32>> {
+    let n = points.len();
+    let mut grid = vec![vec![0 as f32; n]; n];
+
+    let start: Instant = Instant::now();
+    grid.par_iter_mut().enumerate().for_each(|(i, row)| {
+        for j in 0..n {
+            if i != j {
+                row[j] = f(&points[i], &points[j]);
+            }
+        }
+    });
+    if MEASURE_TIMING {
+        let duration = start.elapsed();
+        debug!("Edge grid took {duration:?}");
+    }
+
+    debug!("{}", format_grid(&grid));
+
+    grid
+}
+
+/// Checks a grid is usable by the solvers: at least one city, and n rows of n distances
+pub fn validate_grid<D>(grid: &[Vec<D>]) -> Result<(), TspError> {
+    if grid.is_empty() {
+        return Err(TspError::EmptyInput);
+    }
+    let n = grid.len();
+    if grid.iter().any(|row| row.len() != n) {
+        return Err(TspError::NonSquareGrid);
+    }
+
+    Ok(())
+}
+
+/// True when grid[i][j] == grid[j][i] for every pair of cities
+pub fn is_symmetric<D: PartialEq>(grid: &[Vec<D>]) -> bool {
+    let n = grid.len();
+    (0..n).all(|i| (i+1..n).all(|j| grid[i][j] == grid[j][i]))
+}
+
+/// Adjustment applied by `preprocess` between building a grid and solving it
+#[derive(Clone, Debug, PartialEq)]
+pub enum GridOp {
+    /// Multiply every distance, e.g. to convert units
+    Scale(f32),
+    /// Add to every distance, e.g. a fixed cost per stop
+    Offset(f32),
+    /// Limit every distance to `[min, max]`
+    Clamp { min: f32, max: f32 },
+    /// Add `extra` to the directed edge `from -> to` only
+    Penalize { from: usize, to: usize, extra: f32 },
+    /// Cost of staying at a city, 0 when built
+    Diagonal(f32),
+}
+
+/// Applies `ops` to the grid in order. Apart from `Diagonal`, they leave grid[i][i] alone.
+pub fn preprocess(grid: &mut [Vec<f32>], ops: &[GridOp]) -> Result<(), TspError> {
+    validate_grid(grid)?;
+    let n = grid.len();
+
+    for op in ops {
+        if let GridOp::Penalize { from, to, extra } = *op {
+            if from >= n || to >= n {
+                return Err(TspError::InvalidTour(format!("penalized edge ({from}, {to}) refers to a city out of range")));
+            }
+            grid[from][to] += extra;
+            continue;
+        }
+        for (i, row) in grid.iter_mut().enumerate() {
+            for (j, distance) in row.iter_mut().enumerate() {
+                match *op {
+                    GridOp::Diagonal(d) if i == j => *distance = d,
+                    _ if i == j => {}
+                    GridOp::Scale(factor) => *distance *= factor,
+                    GridOp::Offset(amount) => *distance += amount,
+                    GridOp::Clamp { min, max } => *distance = distance.clamp(min, max),
+                    GridOp::Penalize { .. } | GridOp::Diagonal(_) => {}
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Shortest real distance between every pair of cities in a road network, where `sparse[i][j]` is
+/// the direct road from i to j and `f32::INFINITY` marks a missing one. Pairs with no connecting
+/// route at all stay infinite.
+pub fn floyd_warshall(sparse: &[Vec<f32>]) -> Vec<Vec<f32>> {
+    let n = sparse.len();
+    let mut dist = sparse.to_vec();
+    for (i, row) in dist.iter_mut().enumerate() {
+        row[i] = 0.0;
+    }
+
+    for k in 0..n {
+        for i in 0..n {
+            for j in 0..n {
+                let via = dist[i][k] + dist[k][j];
+                if via < dist[i][j] {
+                    dist[i][j] = via;
+                }
+            }
+        }
+    }
+
+    dist
+}
+
+/// Triples `(i, j, k)` of distinct cities where going via `j` is shorter than the direct edge,
+/// `d(i,k) > d(i,j) + d(j,k)`. Heuristics with a bound on how far they are from the optimum (and the
+/// hull shortcut) assume there are none. A relative tolerance absorbs `f32` rounding of true metrics.
+pub fn triangle_violations(grid: &[Vec<f32>]) -> Vec<(usize, usize, usize)> {
+    let n = grid.len();
+    let mut violations = Vec::new();
+    for i in 0..n {
+        for j in 0..n {
+            for k in 0..n {
+                if i == j || j == k || i == k {
+                    continue;
+                }
+                let detour = grid[i][j] + grid[j][k];
+                if grid[i][k] > detour + detour.abs() * TRIANGLE_TOLERANCE {
+                    violations.push((i, j, k));
+                }
+            }
+        }
+    }
+
+    violations
+}
+
+/// Element-wise `a - b` of two grids over the same cities
+pub fn grid_diff(a: &[Vec<f32>], b: &[Vec<f32>]) -> Result<Vec<Vec<f32>>, TspError> {
+    validate_grid(a)?;
+    validate_grid(b)?;
+    if a.len() != b.len() {
+        return Err(TspError::SizeMismatch(a.len(), b.len()));
+    }
+
+    let n = a.len();
+    let mut diff = vec![vec![0 as f32; n]; n];
+    for i in 0..n {
+        for j in 0..n {
+            diff[i][j] = a[i][j] - b[i][j];
+        }
+    }
+
+    Ok(diff)
+}
+
+/// Neighbour lists: the `k` closest other cities of each city, nearest first. Candidate lists like
+/// these keep local search from trying every pair of cities.
+pub fn k_nearest(grid: &[Vec<f32>], k: usize) -> Vec<Vec<usize>> {
+    let n = grid.len();
+    (0..n)
+        .map(|city| {
+            let mut others: Vec<usize> = (0..n).filter(|&other| other != city).collect();
+            others.sort_by(|&x, &y| grid[city][x].total_cmp(&grid[city][y]));
+            others.truncate(k);
+            others
+        })
+        .collect()
+}
+
+/// Pairs `(i, j)`, `i < j`, where each city is the other's nearest neighbour, in order of `i`.
+/// Such pairs are nearly always adjacent in an optimal tour. Ties go to the lower-numbered city.
+pub fn mutual_nearest_neighbors(grid: &[Vec<f32>]) -> Vec<(usize, usize)> {
+    let nearest: Vec<Option<usize>> = k_nearest(grid, 1).into_iter().map(|list| list.first().copied()).collect();
+    (0..grid.len())
+        .filter_map(|i| nearest[i].map(|j| (i, j)))
+        .filter(|&(i, j)| i < j && nearest[j] == Some(i))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::brute_force::brute_force;
+    use crate::point::{generate_points_with, Point};
+    use crate::tour::tour_edges;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn custom_metric_adds_a_toll_to_every_edge() {
+        const TOLL: f32 = 2.5;
+        let points: Vec<Point> = [(3.5, 7.0), (50.0, 1.25), (99.0, 40.5), (12.0, 88.0), (60.0, 60.0), (0.0, 0.0)]
+            .iter()
+            .map(|&(x, y)| Point { x, y, label: None })
+            .collect();
+        let plain = calculate_edge_grid_with(&points, Point::distance);
+        let tolled = calculate_edge_grid_with(&points, |a, b| a.distance(b) + TOLL);
+
+        for i in 0..points.len() {
+            for j in 0..points.len() {
+                let expected = if i == j { 0.0 } else { plain[i][j] + TOLL };
+                assert_eq!(tolled[i][j], expected, "{i} > {j}");
+            }
+        }
+    }
+
+    #[test]
+    fn diff_of_two_small_grids() {
+        let a = vec![vec![0.0, 3.0, 4.0], vec![3.0, 0.0, 5.0], vec![4.0, 5.0, 0.0]];
+        let b = vec![vec![0.0, 2.0, 4.0], vec![3.5, 0.0, 1.0], vec![4.0, 5.0, 0.0]];
+
+        let diff = grid_diff(&a, &b).unwrap();
+        assert_eq!(diff, vec![vec![0.0, 1.0, 0.0], vec![-0.5, 0.0, 4.0], vec![0.0, 0.0, 0.0]]);
+        assert_eq!(crate::display::format_diff_summary(&diff), "max |diff|: 4.00, mean |diff|: 0.92");
+        assert!(matches!(grid_diff(&a, &[vec![0.0]]), Err(TspError::SizeMismatch(3, 1))));
+    }
+
+    #[test]
+    fn long_way_round_is_a_triangle_violation() {
+        let grid = vec![vec![0.0, 1.0, 10.0], vec![1.0, 0.0, 1.0], vec![10.0, 1.0, 0.0]];
+        assert_eq!(triangle_violations(&grid), vec![(0, 1, 2), (2, 1, 0)]);
+
+        let points = generate_points_with(20, 100, false, &mut StdRng::seed_from_u64(3)).unwrap();
+        assert!(triangle_violations(&calculate_edge_grid(points).unwrap()).is_empty());
+    }
+
+    #[test]
+    fn missing_edge_is_avoided_and_routed_around() {
+        let square = [(0.0, 0.0), (3.0, 0.0), (3.0, 4.0), (0.0, 4.0)];
+        let points = square.iter().map(|&(x, y)| Point { x, y, label: None }).collect();
+        let mut sparse = calculate_edge_grid(points).unwrap();
+        sparse[0][1] = f32::INFINITY;
+        sparse[1][0] = f32::INFINITY;
+
+        let (tour, length) = brute_force(&sparse).unwrap();
+        assert_eq!(tour, vec![0, 2, 1, 3]);
+        assert_eq!(length, 18.0);
+
+        let closure = floyd_warshall(&sparse);
+        assert_eq!(closure[0][1], 9.0);
+        assert_eq!(closure[1][0], 9.0);
+        assert_eq!(closure[0][2], 5.0);
+
+        let mut split = vec![vec![f32::INFINITY; 3]; 3];
+        split[0][1] = 1.0;
+        assert_eq!(floyd_warshall(&split)[0][2], f32::INFINITY);
+        assert_eq!(floyd_warshall(&split)[2][2], 0.0);
+    }
+
+    #[test]
+    fn preprocessing_scales_lengths_and_penalties_move_the_tour() {
+        let points = generate_points_with(6, 100, false, &mut StdRng::seed_from_u64(8)).unwrap();
+        let grid = calculate_edge_grid(points).unwrap();
+        let (tour, length) = brute_force(&grid).unwrap();
+
+        let mut scaled = grid.clone();
+        preprocess(&mut scaled, &[GridOp::Scale(2.0), GridOp::Diagonal(0.0)]).unwrap();
+        let (scaled_tour, scaled_length) = brute_force(&scaled).unwrap();
+        assert_eq!(scaled_tour, tour);
+        assert!((scaled_length - 2.0 * length).abs() <= length * 1e-5);
+
+        // charging for both directions of an edge on the optimal tour pushes the search off it
+        let (from, to) = (tour[0], tour[1]);
+        let mut tolled = grid.clone();
+        let toll = |from, to| GridOp::Penalize { from, to, extra: 1000.0 };
+        let tolls = [toll(from, to), toll(to, from)];
+        preprocess(&mut tolled, &tolls).unwrap();
+        let (detour, _) = brute_force(&tolled).unwrap();
+        assert!(!tour_edges(&detour).contains(&(from.min(to), from.max(to))));
+
+        assert!(preprocess(&mut tolled, &[GridOp::Penalize { from: 0, to: 6, extra: 1.0 }]).is_err());
+    }
+
+    #[test]
+    fn close_pairs_are_mutual_nearest_neighbours() {
+        // city 2's nearest is 1, but 1 is closer still to 0, so 2 pairs with nobody
+        let line = [0.0, 1.0, 10.0, 20.0, 21.0].iter().map(|&x| Point { x, y: 0.0, label: None }).collect();
+        let grid = calculate_edge_grid(line).unwrap();
+        assert_eq!(mutual_nearest_neighbors(&grid), vec![(0, 1), (3, 4)]);
+        assert!(mutual_nearest_neighbors(&[vec![0.0]]).is_empty());
+    }
+
+    #[test]
+    fn parallel_grid_matches_serial() {
+        let points = generate_points_with(300, 1000, false, &mut StdRng::seed_from_u64(7)).unwrap();
+        let mut serial = vec![vec![0.0; points.len()]; points.len()];
+        for (i, row) in serial.iter_mut().enumerate() {
+            for (j, distance) in row.iter_mut().enumerate() {
+                if i != j {
+                    *distance = points[i].distance(&points[j]);
+                }
+            }
+        }
+
+        assert_eq!(calculate_edge_grid_with(&points, Point::distance), serial);
+        // and the same bits however many threads share the rows
+        let one_thread = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        assert_eq!(one_thread.install(|| calculate_edge_grid_with(&points, Point::distance)), serial);
+    }
+}
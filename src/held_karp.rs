@@ -0,0 +1,387 @@
+use log::debug;
+
+use crate::error::TspError;
+use crate::grid::validate_grid;
+use crate::objective::StopLimit;
+
+/// Each city's predecessor in the table is stored in a `u8`, and subsets index a `Vec`
+pub const MAX_CITIES: usize = 32;
+/// Marks a city entered straight from the start
+const FROM_START: u8 = u8::MAX;
+
+/// Exact dynamic program over subsets: best[S][j] is the shortest path from city 0 through every
+/// city in S, ending at j. O(2^n n^2) time instead of brute force's (n-1)!.
+///
+/// The full table keeps every subset's costs. With `low_memory` subsets are processed in order of
+/// size and only the costs of the previous size are kept, so what stays for the whole run is the
+/// one-byte predecessor table. Both visit candidates in the same order and give identical tours.
+pub fn held_karp(grid: &[Vec<f32>], low_memory: bool) -> Result<(Vec<usize>, f32), TspError> {
+    validate_grid(grid)?;
+    let n = grid.len();
+    if n > MAX_CITIES {
+        return Err(TspError::TooManyCities(MAX_CITIES, n));
+    }
+    if n == 1 {
+        return Ok((vec![0], 0.0));
+    }
+
+    // cities 1..n are bits 0..m of a subset
+    let m = n - 1;
+    let full = (1usize << m) - 1;
+    let mut parent = vec![FROM_START; (full + 1) * m];
+    let last_costs = if low_memory { layered(grid, &mut parent) } else { full_table(grid, &mut parent) };
+    debug!("Held-Karp predecessor table: {} bytes", parent.len());
+
+    let mut min = f32::INFINITY;
+    let mut last = 0;
+    for j in 0..m {
+        let length = last_costs[j] + grid[j+1][0];
+        if length < min {
+            min = length;
+            last = j;
+        }
+    }
+
+    // every tour needs a missing (infinite) edge
+    if min.is_infinite() {
+        return Err(TspError::Infeasible);
+    }
+
+    Ok((walk_back(&parent, m, full, last), min))
+}
+
+/// Exact shortest Hamiltonian path from `start` to `end` through every city, with no edge back:
+/// Held-Karp over the grid relabelled so `start` is city 0, read off at `end` instead of being
+/// closed into a tour. Uses the full table, so it needs what `held_karp_table_bytes(n, false)` says.
+pub fn shortest_hamiltonian_path(grid: &[Vec<f32>], start: usize, end: usize) -> Result<(Vec<usize>, f32), TspError> {
+    validate_grid(grid)?;
+    let n = grid.len();
+    if n > MAX_CITIES {
+        return Err(TspError::TooManyCities(MAX_CITIES, n));
+    }
+    for city in [start, end] {
+        if city >= n {
+            return Err(TspError::InvalidTour(format!("city {city} is out of range")));
+        }
+    }
+    if n == 1 {
+        return Ok((vec![start], 0.0));
+    }
+    if start == end {
+        return Err(TspError::InvalidTour(format!("the path can't both start and end at city {start}")));
+    }
+
+    let mut order = vec![start];
+    order.extend((0..n).filter(|&city| city != start));
+    let relabelled: Vec<Vec<f32>> = order.iter().map(|&a| order.iter().map(|&b| grid[a][b]).collect()).collect();
+
+    let m = n - 1;
+    let mut parent = vec![FROM_START; (1usize << m) * m];
+    let last_costs = full_table(&relabelled, &mut parent);
+    // relabelled city j + 1, bit j of a subset; cities after `start` moved down one
+    let j = if end < start { end } else { end - 1 };
+    if last_costs[j].is_infinite() {
+        return Err(TspError::Infeasible);
+    }
+
+    let path = walk_back(&parent, m, (1 << m) - 1, j).into_iter().map(|city| order[city]).collect();
+    Ok((path, last_costs[j]))
+}
+
+/// Tour from city 0 that may leave cities out, e.g. when a delivery isn't worth the detour:
+/// skipping city i costs `skip_penalties[i]` (`f32::INFINITY` makes it mandatory), and every stop
+/// past `limit.max_stops` costs `limit.over_penalty`. Returns the tour minimising its length plus
+/// those charges, and that total. Exact, as Held-Karp's table holds the best tour through every
+/// subset of cities; city 0 is the depot and always in the tour, its penalty ignored.
+pub fn choose_stops(grid: &[Vec<f32>], skip_penalties: &[f32], limit: StopLimit) -> Result<(Vec<usize>, f32), TspError> {
+    validate_grid(grid)?;
+    let n = grid.len();
+    if n > MAX_CITIES {
+        return Err(TspError::TooManyCities(MAX_CITIES, n));
+    }
+    if skip_penalties.len() != n {
+        return Err(TspError::SizeMismatch(n, skip_penalties.len()));
+    }
+
+    let m = n - 1;
+    let full = (1usize << m) - 1;
+    let mut parent = vec![FROM_START; (full + 1) * m];
+    let cost = subset_table(grid, &mut parent);
+    let skipped = |mask: usize| (0..m).filter(|i| mask & 1 << i == 0).map(|i| skip_penalties[i+1]).sum::<f32>();
+
+    // the tour of just the depot
+    let mut best = (skipped(0) + limit.penalty(0), 0, None);
+    for mask in 1..=full {
+        let charges = skipped(mask) + limit.penalty(mask.count_ones() as usize);
+        for j in bits(mask) {
+            let total = cost[mask * m + j] + grid[j+1][0] + charges;
+            if total < best.0 {
+                best = (total, mask, Some(j));
+            }
+        }
+    }
+
+    let (total, mask, last) = best;
+    if total.is_infinite() {
+        return Err(TspError::Infeasible);
+    }
+    let tour = match last {
+        Some(last) => walk_back(&parent, m, mask, last),
+        None => vec![0],
+    };
+    debug!("Chose {} of {} cities for a total of {total}", tour.len(), n);
+
+    Ok((tour, total))
+}
+
+/// Follows the predecessors back from the path through `mask` ending at bit `last` to the start,
+/// returning it in visiting order from city 0
+fn walk_back(parent: &[u8], m: usize, mut mask: usize, last: usize) -> Vec<usize> {
+    let mut path = Vec::with_capacity(m + 1);
+    let mut city = last as u8;
+    while city != FROM_START {
+        path.push(city as usize + 1);
+        let previous = parent[mask * m + city as usize];
+        mask ^= 1 << city;
+        city = previous;
+    }
+    path.push(0);
+    path.reverse();
+
+    path
+}
+
+/// Bytes `held_karp` allocates for its tables on n cities. Subsets are of the n-1 cities after the
+/// start, so the full table is 2^(n-1) * (n-1) `f32` costs, one per subset and last city, plus a
+/// `u8` predecessor table of the same shape. `low_memory` keeps the predecessors but only ever holds
+/// the costs of two neighbouring subset sizes; layer sizes are binomial coefficients, so this is
+/// what the two largest need.
+pub fn held_karp_table_bytes(n: usize, low_memory: bool) -> usize {
+    if n < 2 {
+        return 0;
+    }
+    let m = n - 1;
+    let entries = (1usize << m) * m;
+    let costs = if low_memory {
+        let binomial = binomials(m);
+        (1..=m).map(|k| (binomial[m][k] + binomial[m][k-1]) * m).max().unwrap_or(0)
+    } else {
+        entries
+    };
+
+    costs * size_of::<f32>() + entries * size_of::<u8>()
+}
+
+/// Cost of ending the path through S at j, from the best path through S without j
+fn extend(grid: &[Vec<f32>], mask: usize, j: usize, previous_cost: impl Fn(usize, usize) -> f32) -> (f32, u8) {
+    let rest = mask ^ 1 << j;
+    if rest == 0 {
+        return (grid[0][j+1], FROM_START);
+    }
+
+    let mut best = (f32::INFINITY, FROM_START);
+    for i in bits(rest) {
+        let cost = previous_cost(rest, i) + grid[i+1][j+1];
+        if cost < best.0 {
+            best = (cost, i as u8);
+        }
+    }
+
+    best
+}
+
+/// Costs for every subset, indexed by the subset itself; returns the row of the full set
+fn full_table(grid: &[Vec<f32>], parent: &mut [u8]) -> Vec<f32> {
+    let m = grid.len() - 1;
+    let cost = subset_table(grid, parent);
+
+    cost[((1usize << m) - 1) * m..].to_vec()
+}
+
+/// The whole cost table: `cost[mask * m + j]` is the shortest path from city 0 through the
+/// cities of `mask`, ending at bit j
+fn subset_table(grid: &[Vec<f32>], parent: &mut [u8]) -> Vec<f32> {
+    let m = grid.len() - 1;
+    let full = (1usize << m) - 1;
+    let mut cost = vec![f32::INFINITY; (full + 1) * m];
+
+    // every proper subset of S is numerically smaller than S, so it is already filled in
+    for mask in 1..=full {
+        for j in bits(mask) {
+            let (c, p) = extend(grid, mask, j, |rest, i| cost[rest * m + i]);
+            cost[mask * m + j] = c;
+            parent[mask * m + j] = p;
+        }
+    }
+
+    cost
+}
+
+/// Costs for one subset size at a time, each subset indexed by its rank among subsets of that size
+#[allow(clippy::needless_range_loop, reason = "k is the subset size being built, not just a position in binomial[m]")]
+fn layered(grid: &[Vec<f32>], parent: &mut [u8]) -> Vec<f32> {
+    let m = grid.len() - 1;
+    let binomial = binomials(m);
+    let rank = |mask: usize| bits(mask).enumerate().map(|(t, p)| binomial[p][t + 1]).sum::<usize>();
+
+    let mut previous: Vec<f32> = Vec::new();
+    for k in 1..=m {
+        let mut current = vec![f32::INFINITY; binomial[m][k] * m];
+        // Gosper's hack: subsets of size k in increasing order, which is also rank order
+        let mut mask = (1usize << k) - 1;
+        while mask < 1 << m {
+            let r = rank(mask);
+            for j in bits(mask) {
+                let (c, p) = extend(grid, mask, j, |rest, i| previous[rank(rest) * m + i]);
+                current[r * m + j] = c;
+                parent[mask * m + j] = p;
+            }
+            let low = mask & mask.wrapping_neg();
+            let ripple = mask + low;
+            mask = (((ripple ^ mask) >> 2) / low) | ripple;
+        }
+        previous = current;
+    }
+
+    previous
+}
+
+/// binomial[a][b] = a choose b, for a up to m
+fn binomials(m: usize) -> Vec<Vec<usize>> {
+    let mut binomial = vec![vec![0; m + 2]; m + 1];
+    for a in 0..=m {
+        binomial[a][0] = 1;
+        for b in 1..=a {
+            binomial[a][b] = binomial[a-1][b-1] + binomial[a-1][b];
+        }
+    }
+
+    binomial
+}
+
+/// Positions of the set bits, lowest first
+fn bits(mut mask: usize) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        if mask == 0 {
+            return None;
+        }
+        let bit = mask.trailing_zeros() as usize;
+        mask &= mask - 1;
+        Some(bit)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::calculate_edge_grid;
+    use crate::point::generate_points_with;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    /// Seeded random cities, and with `asymmetric` each direction stretched independently
+    fn random_grid(n: usize, seed: u64, asymmetric: bool) -> Vec<Vec<f32>> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut grid = calculate_edge_grid(generate_points_with(n, 100, false, &mut rng).unwrap()).unwrap();
+        if asymmetric {
+            for distance in grid.iter_mut().flatten() {
+                *distance *= rng.gen_range(1.0..1.5);
+            }
+        }
+        grid
+    }
+
+    fn same_length(a: f32, b: f32) -> bool {
+        (a - b).abs() <= 1e-4 * a.max(1.0)
+    }
+
+    #[test]
+    fn layered_table_gives_the_same_tour_as_the_full_one() {
+        for n in 1..=11 {
+            for asymmetric in [false, true] {
+                let grid = random_grid(n, n as u64, asymmetric);
+                assert_eq!(held_karp(&grid, true).unwrap(), held_karp(&grid, false).unwrap(), "n={n}");
+            }
+        }
+    }
+
+    #[test]
+    fn table_bytes_follow_the_subset_counts() {
+        assert_eq!(held_karp_table_bytes(1, false), 0);
+        for n in 2..=20 {
+            let entries = (1 << (n - 1)) * (n - 1);
+            assert_eq!(held_karp_table_bytes(n, false), entries * (size_of::<f32>() + size_of::<u8>()), "n = {n}");
+        }
+        // the two biggest neighbouring layers are the 3 one-city and 3 two-city subsets
+        assert_eq!(held_karp_table_bytes(4, true), (3 + 3) * 3 * size_of::<f32>() + 24 * size_of::<u8>());
+        for n in 2..=20 {
+            assert!(held_karp_table_bytes(n, true) <= held_karp_table_bytes(n, false), "n = {n}");
+        }
+    }
+
+    /// Length of the shortest path over every ordering of the cities between `start` and `end`
+    fn enumerated_path(grid: &[Vec<f32>], start: usize, end: usize) -> f32 {
+        fn extend(grid: &[Vec<f32>], path: &mut Vec<usize>, end: usize, best: &mut f32) {
+            let n = grid.len();
+            if path.len() == n - 1 {
+                let length = path.windows(2).map(|w| grid[w[0]][w[1]]).sum::<f32>() + grid[path[n - 2]][end];
+                *best = best.min(length);
+                return;
+            }
+            for city in 0..n {
+                if city != end && !path.contains(&city) {
+                    path.push(city);
+                    extend(grid, path, end, best);
+                    path.pop();
+                }
+            }
+        }
+        let mut best = f32::INFINITY;
+        extend(grid, &mut vec![start], end, &mut best);
+        best
+    }
+
+    #[test]
+    fn hamiltonian_path_matches_enumerating_every_path() {
+        for seed in 0..10 {
+            let grid = random_grid(6, seed, seed % 2 == 1);
+            for (start, end) in [(0, 5), (5, 0), (2, 4), (3, 1)] {
+                let (path, length) = shortest_hamiltonian_path(&grid, start, end).unwrap();
+                assert_eq!((path[0], path[5]), (start, end));
+                let mut visited = path.clone();
+                visited.sort_unstable();
+                assert_eq!(visited, (0..6).collect::<Vec<_>>());
+                assert!(same_length(length, path.windows(2).map(|w| grid[w[0]][w[1]]).sum()));
+                assert!(same_length(length, enumerated_path(&grid, start, end)));
+            }
+        }
+
+        let grid = random_grid(4, 0, false);
+        assert!(matches!(shortest_hamiltonian_path(&grid, 1, 1), Err(TspError::InvalidTour(_))));
+        assert!(matches!(shortest_hamiltonian_path(&grid, 0, 4), Err(TspError::InvalidTour(_))));
+        assert_eq!(shortest_hamiltonian_path(&[vec![0.0]], 0, 0).unwrap(), (vec![0], 0.0));
+    }
+
+    #[test]
+    fn tightening_max_stops_changes_the_chosen_stops() {
+        // the depot and three cities on a line, the far one the costliest to skip
+        let xs = [0.0f32, 1.0, 2.0, 10.0];
+        let grid: Vec<Vec<f32>> = xs.iter().map(|a| xs.iter().map(|b| (a - b).abs()).collect()).collect();
+        let skip_penalties = [0.0, 5.0, 5.0, 30.0];
+
+        // out to 10 and back takes in 1 and 2 on the way
+        let (mut tour, total) = choose_stops(&grid, &skip_penalties, StopLimit::default()).unwrap();
+        tour.sort_unstable();
+        assert_eq!((tour, total), (vec![0, 1, 2, 3], 20.0));
+
+        // one stop: 10 and back, skipping 1 and 2 for 10 more, beats a 100 for each extra stop
+        let limit = StopLimit { max_stops: Some(1), over_penalty: 100.0 };
+        assert_eq!(choose_stops(&grid, &skip_penalties, limit).unwrap(), (vec![0, 3], 30.0));
+
+        // a gentle penalty is worth paying to keep both
+        let limit = StopLimit { max_stops: Some(1), over_penalty: 1.0 };
+        assert_eq!(choose_stops(&grid, &skip_penalties, limit).unwrap().1, 22.0);
+        assert_eq!(limit.penalty(1), 0.0);
+        assert_eq!(limit.penalty(3), 2.0);
+    }
+}
@@ -0,0 +1,403 @@
+use rayon::prelude::*;
+
+use crate::error::TspError;
+use crate::grid::{calculate_edge_grid, validate_grid};
+use crate::local_search::two_opt;
+use crate::objective::{path_distance, ReturnPolicy};
+use crate::point::Point;
+use crate::tour::{rotate_to, tour_length};
+
+/// Greedy tour from `start`: always move to the closest city not yet visited
+pub fn nearest_neighbor(grid: &[Vec<f32>], start: usize) -> Result<(Vec<usize>, f32), TspError> {
+    validate_grid(grid)?;
+    let n = grid.len();
+    if start >= n {
+        return Err(TspError::InvalidTour(format!("start city {start} is out of range for {n} cities")));
+    }
+
+    let mut path = vec![start];
+    let mut visited = vec![false; n];
+    visited[start] = true;
+    let mut total_distance = 0.0;
+
+    let mut current = start;
+    for _ in 1..n {
+        let mut next = usize::MAX;
+        for j in 0..n {
+            if !visited[j] && (next == usize::MAX || grid[current][j] < grid[current][next]) {
+                next = j;
+            }
+        }
+        visited[next] = true;
+        total_distance += grid[current][next];
+        path.push(next);
+        current = next;
+    }
+    total_distance += grid[current][start];
+
+    Ok((path, total_distance))
+}
+
+/// Runs nearest neighbor from each of the candidate `starts` (typically `0..n`) and keeps the shortest tour.
+/// The starts run in parallel; ties go to the earliest start, so the result matches a serial run.
+pub fn nearest_neighbor_multistart<I: IntoIterator<Item = usize>>(grid: &[Vec<f32>], starts: I) -> Result<(Vec<usize>, f32), TspError> {
+    let starts: Vec<usize> = starts.into_iter().collect();
+    let candidates = starts.par_iter()
+        .map(|&start| nearest_neighbor(grid, start))
+        .collect::<Result<Vec<_>, TspError>>()?;
+
+    let mut best: Option<(Vec<usize>, f32)> = None;
+    for candidate in candidates {
+        if best.as_ref().is_none_or(|b| candidate.1 < b.1) {
+            best = Some(candidate);
+        }
+    }
+
+    best.ok_or(TspError::EmptyInput)
+}
+
+/// Builds a tour from city 0 by repeatedly inserting the city whose insertion adds the least distance
+///
+/// Each pending city caches its cheapest edge (a, b) to insert into. After an insertion splits an edge
+/// only the cities whose cached edge was the one removed need a full rescan; the rest just compare
+/// against the two new edges, keeping the whole build at `O(n²)` instead of `O(n³)`.
+pub fn cheapest_insertion(grid: &[Vec<f32>]) -> Result<(Vec<usize>, f32), TspError> {
+    validate_grid(grid)?;
+    let n = grid.len();
+    if n < 3 {
+        let path: Vec<usize> = (0..n).collect();
+        let total_distance = if n == 2 { grid[0][1] + grid[1][0] } else { 0.0 };
+        return Ok((path, total_distance));
+    }
+
+    let insertion_cost = |a: usize, b: usize, c: usize| grid[a][c] + grid[c][b] - grid[a][b];
+    let best_edge = |path: &[usize], c: usize| -> (f32, usize, usize) {
+        let mut best = (f32::MAX, 0, 0);
+        for p in 0..path.len() {
+            let a = path[p];
+            let b = path[(p + 1) % path.len()];
+            let cost = insertion_cost(a, b, c);
+            if cost < best.0 {
+                best = (cost, a, b);
+            }
+        }
+        best
+    };
+
+    let mut path = vec![0];
+    let mut nearest = 1;
+    for j in 2..n {
+        if grid[0][j] < grid[0][nearest] {
+            nearest = j;
+        }
+    }
+    path.push(nearest);
+    let mut total_distance = grid[0][nearest] + grid[nearest][0];
+
+    let mut pending: Vec<usize> = (1..n).filter(|c| *c != nearest).collect();
+    let mut cache: Vec<(f32, usize, usize)> = pending.iter().map(|c| best_edge(&path, *c)).collect();
+
+    while !pending.is_empty() {
+        let mut k = 0;
+        for i in 1..pending.len() {
+            if cache[i].0 < cache[k].0 {
+                k = i;
+            }
+        }
+        let city = pending.swap_remove(k);
+        let (cost, a, b) = cache.swap_remove(k);
+
+        let position = path.iter().position(|x| *x == a).unwrap();
+        path.insert(position + 1, city);
+        total_distance += cost;
+
+        for i in 0..pending.len() {
+            let c = pending[i];
+            if cache[i].1 == a && cache[i].2 == b {
+                cache[i] = best_edge(&path, c);
+            } else {
+                let via_a = insertion_cost(a, city, c);
+                if via_a < cache[i].0 {
+                    cache[i] = (via_a, a, city);
+                }
+                let via_b = insertion_cost(city, b, c);
+                if via_b < cache[i].0 {
+                    cache[i] = (via_b, city, b);
+                }
+            }
+        }
+    }
+
+    Ok((path, total_distance))
+}
+
+/// Greedy fragment merging: every city starts as its own fragment, and the closest pair of
+/// fragment endpoints is joined until a single path remains, which is then closed. Union-find
+/// keeps two ends of the same fragment from being joined into an early subcycle.
+pub fn fragment_merge(grid: &[Vec<f32>]) -> Result<(Vec<usize>, f32), TspError> {
+    validate_grid(grid)?;
+    let n = grid.len();
+
+    let mut edges: Vec<(usize, usize)> = (0..n).flat_map(|i| (i+1..n).map(move |j| (i, j))).collect();
+    edges.sort_by(|&(a, b), &(c, d)| grid[a][b].total_cmp(&grid[c][d]));
+
+    let mut fragment: Vec<usize> = (0..n).collect();
+    let mut neighbours: Vec<Vec<usize>> = vec![Vec::new(); n];
+    let mut joins = 0;
+    for (a, b) in edges {
+        if joins == n.saturating_sub(1) {
+            break;
+        }
+        // only fragment endpoints can be joined
+        if neighbours[a].len() == 2 || neighbours[b].len() == 2 {
+            continue;
+        }
+        let (root_a, root_b) = (find(&mut fragment, a), find(&mut fragment, b));
+        if root_a == root_b {
+            continue;
+        }
+        fragment[root_a] = root_b;
+        neighbours[a].push(b);
+        neighbours[b].push(a);
+        joins += 1;
+    }
+
+    // walk the single remaining path from one of its ends, then rotate it to start at 0
+    let mut path = Vec::with_capacity(n);
+    let mut previous = usize::MAX;
+    let mut city = (0..n).find(|&c| neighbours[c].len() < 2).unwrap_or(0);
+    for _ in 0..n {
+        path.push(city);
+        let next = neighbours[city].iter().copied().find(|&c| c != previous);
+        previous = city;
+        match next {
+            Some(next) => city = next,
+            None => break,
+        }
+    }
+    let tour = rotate_to(&path, 0);
+    let length = tour_length(grid, &tour)?;
+
+    Ok((tour, length))
+}
+
+/// Root of `city`'s fragment, halving the path to it on the way
+fn find(fragment: &mut [usize], mut city: usize) -> usize {
+    while fragment[city] != city {
+        fragment[city] = fragment[fragment[city]];
+        city = fragment[city];
+    }
+
+    city
+}
+
+/// Sweep: visits the cities in order of their angle around the centroid, like a radar beam, then
+/// polishes the tour with 2-opt. Fast, and decent when the cities sit in clusters or around a
+/// ring; cities with the same angle go nearest to the centroid first. Starts at city 0.
+pub fn sweep_heuristic(points: &[Point]) -> Result<(Vec<usize>, f32), TspError> {
+    let grid = calculate_edge_grid(points.to_vec())?;
+    let n = points.len() as f64;
+    let cx = points.iter().map(|p| p.x).sum::<f64>() / n;
+    let cy = points.iter().map(|p| p.y).sum::<f64>() / n;
+
+    let polar = |p: &Point| ((p.y - cy).atan2(p.x - cx), (p.x - cx).hypot(p.y - cy));
+    let mut order: Vec<usize> = (0..points.len()).collect();
+    order.sort_by(|&a, &b| {
+        let ((angle_a, radius_a), (angle_b, radius_b)) = (polar(&points[a]), polar(&points[b]));
+        angle_a.total_cmp(&angle_b).then(radius_a.total_cmp(&radius_b))
+    });
+
+    two_opt(rotate_to(&order, 0), &grid)
+}
+
+/// Joins two disjoint sub-tours into one closed tour through both, e.g. the halves of a
+/// partition-and-merge heuristic: cuts one edge of each and reconnects the four ends the cheaper
+/// way, trying every pair of edges. Returns the merged tour, starting where `a` does, and its
+/// length; an empty tour merges into the other unchanged.
+pub fn merge_tours(a: &[usize], b: &[usize], grid: &[Vec<f32>]) -> Result<(Vec<usize>, f32), TspError> {
+    validate_grid(grid)?;
+    let n = grid.len();
+    let mut seen = vec![false; n];
+    for &city in a.iter().chain(b) {
+        if city >= n {
+            return Err(TspError::InvalidTour(format!("city {city} is out of range")));
+        }
+        if seen[city] {
+            return Err(TspError::InvalidTour(format!("city {city} is in both tours, or twice in one")));
+        }
+        seen[city] = true;
+    }
+    let (a, b) = match (a.is_empty(), b.is_empty()) {
+        (true, true) => return Err(TspError::EmptyInput),
+        (true, false) => (b, a),
+        _ => (a, b),
+    };
+    if b.is_empty() {
+        return Ok((a.to_vec(), path_distance(a, grid, ReturnPolicy::Full)));
+    }
+
+    // cut a[i]-a[i+1] and b[j]-b[j+1], then either a[i] > b[j+1] .. b[j] > a[i+1], or the same
+    // with b walked backwards
+    let (next_a, next_b) = (|i: usize| a[(i + 1) % a.len()], |j: usize| b[(j + 1) % b.len()]);
+    let mut best = (f32::INFINITY, 0, 0, false);
+    for i in 0..a.len() {
+        for j in 0..b.len() {
+            let removed = grid[a[i]][next_a(i)] + grid[b[j]][next_b(j)];
+            let forward = grid[a[i]][next_b(j)] + grid[b[j]][next_a(i)] - removed;
+            let backward = grid[a[i]][b[j]] + grid[next_b(j)][next_a(i)] - removed;
+            if forward < best.0 {
+                best = (forward, i, j, false);
+            }
+            if backward < best.0 {
+                best = (backward, i, j, true);
+            }
+        }
+    }
+
+    let (_, i, j, reversed) = best;
+    let mut spliced: Vec<usize> = b[j + 1..].iter().chain(&b[..=j]).copied().collect();
+    if reversed {
+        spliced.reverse();
+    }
+    let mut merged: Vec<usize> = a[..=i].to_vec();
+    merged.extend(spliced);
+    merged.extend(&a[i + 1..]);
+    let length = path_distance(&merged, grid, ReturnPolicy::Full);
+
+    Ok((merged, length))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::brute_force::brute_force;
+    use crate::grid::calculate_edge_grid;
+    use crate::point::{generate_points_with, Point};
+    use crate::tour::{canonical_tour, tour_edges, tour_length, validate_tour};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn instance(n: usize, seed: u64) -> Vec<Vec<f32>> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let points = (0..n)
+            .map(|_| Point { x: rng.gen_range(0.0..100.0), y: rng.gen_range(0.0..100.0), label: None })
+            .collect();
+        calculate_edge_grid(points).unwrap()
+    }
+
+    #[test]
+    fn two_starts_keep_the_shorter_tour() {
+        let grid = instance(12, 1);
+        let (from_3, from_7) = (nearest_neighbor(&grid, 3).unwrap(), nearest_neighbor(&grid, 7).unwrap());
+        assert_ne!(from_3.1, from_7.1);
+        let shorter = if from_3.1 < from_7.1 { &from_3 } else { &from_7 };
+
+        assert_eq!(&nearest_neighbor_multistart(&grid, [3, 7]).unwrap(), shorter);
+        assert_eq!(&nearest_neighbor_multistart(&grid, vec![7, 3]).unwrap(), shorter);
+        assert!(matches!(nearest_neighbor_multistart(&grid, []), Err(TspError::EmptyInput)));
+    }
+
+    /// Cheapest insertion without the cache: every step rescans every pending city against every edge
+    fn naive_cheapest_insertion(grid: &[Vec<f32>]) -> Vec<usize> {
+        let n = grid.len();
+        let nearest = (1..n).min_by(|&a, &b| grid[0][a].total_cmp(&grid[0][b])).unwrap();
+        let mut path = vec![0, nearest];
+        let mut pending: Vec<usize> = (1..n).filter(|&c| c != nearest).collect();
+        while !pending.is_empty() {
+            let mut best = (f32::MAX, 0, 0);
+            for (k, &c) in pending.iter().enumerate() {
+                for p in 0..path.len() {
+                    let (a, b) = (path[p], path[(p + 1) % path.len()]);
+                    let cost = grid[a][c] + grid[c][b] - grid[a][b];
+                    if cost < best.0 {
+                        best = (cost, k, p);
+                    }
+                }
+            }
+            let (_, k, p) = best;
+            path.insert(p + 1, pending.remove(k));
+        }
+
+        path
+    }
+
+    #[test]
+    fn cached_insertion_matches_a_full_rescan() {
+        for seed in 0..8 {
+            let grid = instance(40, seed);
+            let (tour, length) = cheapest_insertion(&grid).unwrap();
+            assert_eq!(tour, naive_cheapest_insertion(&grid), "seed {seed}");
+            assert!((length - tour_length(&grid, &tour).unwrap()).abs() < 1e-2, "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn parallel_multistart_matches_a_serial_run() {
+        let grid = instance(60, 4);
+        let mut serial: Option<(Vec<usize>, f32)> = None;
+        for start in 0..60 {
+            let candidate = nearest_neighbor(&grid, start).unwrap();
+            if serial.as_ref().is_none_or(|best| candidate.1 < best.1) {
+                serial = Some(candidate);
+            }
+        }
+
+        assert_eq!(nearest_neighbor_multistart(&grid, 0..60).unwrap(), serial.unwrap());
+    }
+
+    #[test]
+    fn fragments_merge_into_one_cycle() {
+        for n in [1, 2, 3, 10, 40] {
+            let grid = instance(n, n as u64);
+            let (tour, length) = fragment_merge(&grid).unwrap();
+            validate_tour(&tour, n).unwrap();
+            assert!((tour_length(&grid, &tour).unwrap() - length).abs() <= length * 1e-5, "n = {n}");
+        }
+
+        // two tight pairs far apart: each pair is joined first, then the pairs are linked
+        let grid = vec![
+            vec![0.0, 1.0, 10.0, 10.0],
+            vec![1.0, 0.0, 10.0, 10.0],
+            vec![10.0, 10.0, 0.0, 1.0],
+            vec![10.0, 10.0, 1.0, 0.0],
+        ];
+        let (tour, length) = fragment_merge(&grid).unwrap();
+        assert_eq!(length, 22.0);
+        assert!(tour_edges(&tour).contains(&(0, 1)) && tour_edges(&tour).contains(&(2, 3)));
+    }
+
+    #[test]
+    fn sweep_follows_a_ring_round() {
+        // city i sits 5 twelfths of the way round from city i-1
+        let angle = |i: usize| (i * 5 % 12) as f64 * std::f64::consts::TAU / 12.0;
+        let at = |angle: f64| Point { x: 50.0 + 30.0 * angle.cos(), y: 50.0 + 30.0 * angle.sin(), label: None };
+        let ring: Vec<Point> = (0..12).map(|i| at(angle(i))).collect();
+        let mut by_angle: Vec<usize> = (0..12).collect();
+        by_angle.sort_by_key(|&i| i * 5 % 12);
+
+        let (tour, _) = sweep_heuristic(&ring).unwrap();
+        assert_eq!(tour[0], 0);
+        assert_eq!(canonical_tour(&tour), canonical_tour(&by_angle));
+
+        let points = generate_points_with(40, 100, false, &mut StdRng::seed_from_u64(5)).unwrap();
+        let (tour, length) = sweep_heuristic(&points).unwrap();
+        validate_tour(&tour, 40).unwrap();
+        assert!((tour_length(&calculate_edge_grid(points).unwrap(), &tour).unwrap() - length).abs() < 1e-2);
+    }
+
+    #[test]
+    fn merging_two_edges_gives_the_optimal_four_city_tour() {
+        // the short sides of a 4 x 1 rectangle, joined by cutting both and adding the long sides
+        let corners = [(0.0, 0.0), (0.0, 1.0), (4.0, 0.0), (4.0, 1.0)];
+        let grid = calculate_edge_grid(corners.iter().map(|&(x, y)| Point { x, y, label: None }).collect()).unwrap();
+        let (tour, length) = merge_tours(&[0, 1], &[2, 3], &grid).unwrap();
+        assert!(validate_tour(&tour, 4).is_ok());
+        assert_eq!(tour[0], 0);
+        assert_eq!(canonical_tour(&tour), vec![0, 1, 3, 2]);
+        assert_eq!(length, 10.0);
+        assert_eq!(length, brute_force(&grid).unwrap().1);
+
+        assert_eq!(merge_tours(&[], &[3, 1], &grid).unwrap(), (vec![3, 1], 8.0));
+        assert!(matches!(merge_tours(&[0, 1], &[1, 2], &grid), Err(TspError::InvalidTour(_))));
+    }
+}
@@ -0,0 +1,72 @@
+use log::debug;
+
+use crate::bound::one_tree_bound;
+use crate::brute_force::branch_and_bound_with_required_edges;
+use crate::error::TspError;
+use crate::grid::validate_grid;
+use crate::heuristics::nearest_neighbor;
+use crate::local_search::two_opt;
+use crate::tour::tour_edges;
+
+/// Exact search over only the part of the tour the heuristics disagree on: edges found by both
+/// nearest neighbor and 2-opt of that tour are fixed, and branch and bound fills in the rest.
+///
+/// The flag is true only when the result is proven optimal, i.e. nothing was fixed or the tour
+/// meets `one_tree_bound`. Otherwise a better tour may exist that drops a fixed edge.
+pub fn hybrid_fix_and_solve(grid: &[Vec<f32>]) -> Result<(Vec<usize>, f32, bool), TspError> {
+    validate_grid(grid)?;
+
+    let (nn_tour, _) = nearest_neighbor(grid, 0)?;
+    let (polished, _) = two_opt(nn_tour.clone(), grid)?;
+    let polished_edges = tour_edges(&polished);
+    // when every edge agrees the whole tour is fixed, and the search only confirms it
+    let fixed: Vec<_> = tour_edges(&nn_tour).into_iter().filter(|e| polished_edges.contains(e)).collect();
+    debug!("Fixed {} of {} edges", fixed.len(), grid.len());
+
+    let (tour, length) = branch_and_bound_with_required_edges(grid, &fixed)?;
+    let proven = fixed.is_empty() || length <= one_tree_bound(grid) * (1.0 + 1e-5);
+
+    Ok((tour, length, proven))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::brute_force::brute_force;
+    use crate::grid::calculate_edge_grid;
+    use crate::point::{generate_points_with, Point};
+    use crate::tour::{tour_length, validate_tour};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn hybrid_is_never_better_than_optimal_and_proof_means_optimal() {
+        for seed in 0..20 {
+            let points = generate_points_with(8, 100, false, &mut StdRng::seed_from_u64(seed)).unwrap();
+            let grid = calculate_edge_grid(points).unwrap();
+            let (tour, length, proven) = hybrid_fix_and_solve(&grid).unwrap();
+            validate_tour(&tour, 8).unwrap();
+            assert!((tour_length(&grid, &tour).unwrap() - length).abs() <= length * 1e-5);
+
+            let (_, optimum) = brute_force(&grid).unwrap();
+            assert!(length >= optimum * (1.0 - 1e-5), "seed {seed}");
+            if proven {
+                assert!(length <= optimum * (1.0 + 1e-5), "seed {seed}");
+            }
+        }
+    }
+
+    #[test]
+    fn fixed_edges_of_an_optimal_tour_are_proven() {
+        // a 3x2 block of unit squares: going round the rim is optimal, both heuristics find it,
+        // and the 1-tree bound meets it
+        let corners = [(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0), (0.0, 1.0)];
+        let grid = calculate_edge_grid(corners.iter().map(|&(x, y)| Point { x, y, label: None }).collect()).unwrap();
+        let (_, optimum) = brute_force(&grid).unwrap();
+
+        let (tour, length, proven) = hybrid_fix_and_solve(&grid).unwrap();
+        validate_tour(&tour, 6).unwrap();
+        assert!(proven);
+        assert_eq!(length, optimum);
+    }
+}
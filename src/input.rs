@@ -0,0 +1,43 @@
+use std::io::{self, BufRead};
+
+use crate::error::TspError;
+use crate::point::Point;
+
+/// Reads one city per line as `x y` or `x,y`, optionally followed by the city's name. Blank lines
+/// are skipped; anything else that doesn't start with two numbers is an `InvalidData` error naming
+/// the line.
+pub fn read_points<R: BufRead>(reader: R) -> Result<Vec<Point>, TspError> {
+    let mut points = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if let Some(point) = parse_point(&line).map_err(|reason| invalid_line(i + 1, &reason))? {
+            points.push(point);
+        }
+    }
+
+    if points.is_empty() {
+        return Err(TspError::EmptyInput);
+    }
+
+    Ok(points)
+}
+
+/// Parses a single `x y [name]` / `x,y[,name]` line, `None` for a blank one. The name is the rest
+/// of the line, so it may contain spaces, e.g. `13.4 52.5 Berlin Mitte`.
+pub fn parse_point(line: &str) -> Result<Option<Point>, String> {
+    let fields: Vec<&str> = line.split(|c: char| c == ',' || c.is_whitespace()).filter(|f| !f.is_empty()).collect();
+    match fields.as_slice() {
+        [] => Ok(None),
+        [x, y, name @ ..] => {
+            let x = x.parse::<f64>().map_err(|_| format!("`{x}` is not a number"))?;
+            let y = y.parse::<f64>().map_err(|_| format!("`{y}` is not a number"))?;
+            let label = if name.is_empty() { None } else { Some(name.join(" ")) };
+            Ok(Some(Point { x, y, label }))
+        }
+        _ => Err(format!("expected 2 coordinates, found {}", fields.len())),
+    }
+}
+
+fn invalid_line(line: usize, reason: &str) -> TspError {
+    TspError::Io(io::Error::new(io::ErrorKind::InvalidData, format!("line {line}: {reason}")))
+}
@@ -0,0 +1,86 @@
+pub mod bound;
+pub mod brute_force;
+pub mod checkpoint;
+pub mod config;
+pub mod difficulty;
+pub mod display;
+pub mod distance;
+pub mod error;
+pub mod genetic;
+pub mod geometry;
+pub mod grid;
+pub mod held_karp;
+pub mod heuristics;
+pub mod hybrid;
+pub mod input;
+pub mod local_search;
+pub mod metric;
+pub mod objective;
+pub mod output;
+pub mod point;
+pub mod problem;
+pub mod registry;
+pub mod repl;
+pub mod selfcheck;
+pub mod snapshot;
+pub mod solve;
+pub mod spatial;
+pub mod tour;
+
+pub use bound::one_tree_bound;
+pub use brute_force::{
+    best_tour_with_adjacency, bottleneck_then_distance, branch_and_bound_anytime, branch_and_bound_counted,
+    branch_and_bound_warm, branch_and_bound_with_required_edges, brute_force, brute_force_checkpointed,
+    brute_force_exact, brute_force_time_dependent, brute_force_to_depth,
+    brute_force_with_edge_tiebreak, brute_force_with_objective, brute_force_with_precedences, brute_force_with_progress,
+    brute_force_with_required_edges, estimate_brute_force_time, exists_tour_under, k_best_tours,
+    optimize_with_fixed_prefix, resume_brute_force,
+};
+pub use checkpoint::Checkpoint;
+pub use config::SolveConfig;
+pub use difficulty::{difficulty, format_difficulty, hardest, Trial};
+pub use display::PlotStyle;
+pub use distance::{round_grid, Distance};
+pub use error::TspError;
+pub use genetic::{genetic, genetic_improvements, GeneticConfig, Improvements};
+pub use geometry::{
+    collinear_groups, convex_hull, edges_cross, is_convex_position, tour_axis_changes, tour_crossings, tour_turn_angles,
+};
+pub use grid::{
+    calculate_edge_grid, calculate_edge_grid_with, floyd_warshall, grid_diff, is_symmetric, k_nearest,
+    mutual_nearest_neighbors, preprocess, triangle_violations, validate_grid, GridOp,
+};
+pub use held_karp::{choose_stops, held_karp, held_karp_table_bytes, shortest_hamiltonian_path};
+pub use heuristics::{
+    cheapest_insertion, fragment_merge, merge_tours, nearest_neighbor, nearest_neighbor_multistart, sweep_heuristic,
+};
+pub use hybrid::hybrid_fix_and_solve;
+pub use input::{parse_point, read_points};
+pub use local_search::{
+    apply_three_opt, iterated_local_search, local_search, three_opt, three_opt_gain, two_opt, two_opt_improvements,
+    two_opt_with_improvements, uncross, IteratedSearchResult, LocalSearchResult, ReconnectType, TwoOptImprovements,
+};
+pub use metric::{calculate_edge_grid_metric, Metric, Unit};
+pub use objective::{path_cost, path_distance, time_dependent_cost, visit_schedule, Objective, ReturnPolicy, StopLimit};
+pub use output::{
+    append_timing_csv, write_markdown_report, write_result, write_svg, OutputFormat, TimingRow, TourResult,
+};
+pub use point::{generate_lattice_points, generate_points, generate_points_with, perturb_points, Point};
+pub use problem::Problem;
+pub use registry::{find_solver, is_exact, solver_names, Solver};
+pub use repl::{Repl, ReplCommand};
+pub use snapshot::Snapshot;
+pub use solve::{available_memory, compare_optima, solution_stability, solve_auto, solve_auto_within, solve_subset};
+pub use spatial::{build_spatial_grid, default_cell_size, k_nearest_points, SpatialGrid};
+pub use tour::{
+    bottleneck_edge, canonical_tour, edge_difference, edge_overlap_percent, optimality_gap, random_tour, rotate_to,
+    sample_tour_lengths, split_into_days, successors_to_tour, tour_edges, tour_hash, tour_length, tour_to_matrix,
+    tour_to_successors, validate_tour, Edge,
+};
+
+pub const MAP_WIDTH: u16 = 100;
+
+pub const SHOW_CAPTION: bool = false;
+
+pub const MEASURE_TIMING: bool = true;
+pub const GRAPH_PIXELS: usize = 50;
@@ -0,0 +1,469 @@
+use rand::seq::index::sample;
+use rand::Rng;
+
+use crate::bound::one_tree_bound;
+use crate::error::TspError;
+use crate::geometry::tour_crossings;
+use crate::grid::{k_nearest, validate_grid};
+use crate::point::Point;
+use crate::tour::{tour_length, validate_tour};
+
+/// Improvements smaller than this are treated as float noise, so the search always terminates
+const EPSILON: f32 = 1e-4;
+/// Closest cities per city that `three_opt` tries to connect to
+const THREE_OPT_NEIGHBOURS: usize = 8;
+
+/// Repeatedly reverses the segment between two edges whenever that shortens the tour, until no
+/// such pair of edges is left. The first city stays in place.
+pub fn two_opt(tour: Vec<usize>, grid: &[Vec<f32>]) -> Result<(Vec<usize>, f32), TspError> {
+    two_opt_with_improvements(tour, grid, |_, _| {})
+}
+
+/// `two_opt`, calling `on_improvement` with the tour and its length after every reversal it
+/// makes, e.g. to animate the search. The length is kept up to date from each move's gain.
+pub fn two_opt_with_improvements(
+    tour: Vec<usize>,
+    grid: &[Vec<f32>],
+    mut on_improvement: impl FnMut(&[usize], f32),
+) -> Result<(Vec<usize>, f32), TspError> {
+    let mut improvements = two_opt_improvements(tour, grid)?;
+    for (tour, length) in improvements.by_ref() {
+        on_improvement(&tour, length);
+    }
+
+    let length = tour_length(grid, &improvements.tour)?;
+    Ok((improvements.tour, length))
+}
+
+/// The same search as `two_opt`, one reversal at a time as it is iterated: yields the tour and its
+/// length after each move, so the lengths strictly decrease and the last tour is what `two_opt`
+/// returns. Yields nothing when the starting tour is already 2-optimal.
+pub fn two_opt_improvements(tour: Vec<usize>, grid: &[Vec<f32>]) -> Result<TwoOptImprovements<'_>, TspError> {
+    validate_grid(grid)?;
+    validate_tour(&tour, grid.len())?;
+    let length = tour_length(grid, &tour)?;
+
+    Ok(TwoOptImprovements { grid, tour, length, i: 0, j: 2, improved: false })
+}
+
+/// Iterator from `two_opt_improvements`
+pub struct TwoOptImprovements<'a> {
+    grid: &'a [Vec<f32>],
+    tour: Vec<usize>,
+    length: f32,
+    /// Next pair of edges to try, (tour[i], tour[i+1]) and (tour[j], tour[j+1])
+    i: usize,
+    j: usize,
+    /// Whether the current pass over every pair has made a move yet
+    improved: bool,
+}
+
+impl Iterator for TwoOptImprovements<'_> {
+    type Item = (Vec<usize>, f32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (grid, n) = (self.grid, self.tour.len());
+        loop {
+            if self.j >= n {
+                self.i += 1;
+                self.j = self.i + 2;
+            }
+            if self.i + 2 >= n {
+                // a whole pass without a move is a local optimum
+                if !self.improved {
+                    return None;
+                }
+                (self.i, self.j, self.improved) = (0, 2, false);
+                continue;
+            }
+
+            let (i, j) = (self.i, self.j);
+            self.j += 1;
+            // edges (a, b) and (c, d); with i == 0 and j == n-1 they share city tour[0]
+            if i == 0 && j == n-1 {
+                continue;
+            }
+            let (a, b) = (self.tour[i], self.tour[i+1]);
+            let (c, d) = (self.tour[j], self.tour[(j+1) % n]);
+            let delta = grid[a][c] + grid[b][d] - grid[a][b] - grid[c][d];
+            if delta < -EPSILON {
+                self.tour[i+1..=j].reverse();
+                self.improved = true;
+                self.length += delta;
+                return Some((self.tour.clone(), self.length));
+            }
+        }
+    }
+}
+
+/// Outcome of running local search on a constructed tour
+pub struct LocalSearchResult {
+    pub tour: Vec<usize>,
+    pub initial_length: f32,
+    pub final_length: f32,
+}
+
+impl LocalSearchResult {
+    /// How much shorter the final tour is, as a percentage of the initial length
+    pub fn improvement_percent(&self) -> f32 {
+        if self.initial_length > 0.0 {
+            (self.initial_length - self.final_length) / self.initial_length * 100.0
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Polishes `tour` with 2-opt, keeping the starting length so the gain can be reported
+pub fn local_search(tour: Vec<usize>, grid: &[Vec<f32>]) -> Result<LocalSearchResult, TspError> {
+    let initial_length = tour_length(grid, &tour)?;
+    let (tour, final_length) = two_opt(tour, grid)?;
+
+    Ok(LocalSearchResult { tour, initial_length, final_length })
+}
+
+/// 2-opt driven by geometry instead of the grid: while two edges cross, reverse the segment between
+/// them. In the plane that always shortens the tour, so by the end no edges cross. The first city
+/// stays in place; the length returned is the Euclidean one.
+pub fn uncross(mut tour: Vec<usize>, points: &[Point]) -> Result<(Vec<usize>, f32), TspError> {
+    validate_tour(&tour, points.len())?;
+
+    while let Some(&(i, j)) = tour_crossings(points, &tour).first() {
+        tour[i+1..=j].reverse();
+    }
+
+    let n = tour.len();
+    let length = (0..n).map(|i| points[tour[i]].distance(&points[tour[(i + 1) % n]])).sum();
+    Ok((tour, length))
+}
+
+/// Ways of reconnecting a tour after cutting three edges, other than putting it back as it was.
+/// With cuts after positions i < j < k the tour is `a | b..c | d..e | f`, b..c being the first
+/// segment and d..e the second.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReconnectType {
+    /// `a c..b d..e f`, a plain 2-opt move
+    ReverseFirst,
+    /// `a b..c e..d f`, a plain 2-opt move
+    ReverseSecond,
+    /// `a c..b e..d f`
+    ReverseBoth,
+    /// `a e..d c..b f`, reversing both segments as one, a 2-opt move over the outer edges
+    ReverseAll,
+    /// `a d..e b..c f`, the segments swapped, both kept in order (or-opt)
+    Swap,
+    /// `a d..e c..b f`
+    SwapReverseFirst,
+    /// `a e..d b..c f`
+    SwapReverseSecond,
+}
+
+impl ReconnectType {
+    pub const ALL: [ReconnectType; 7] = [
+        ReconnectType::ReverseFirst,
+        ReconnectType::ReverseSecond,
+        ReconnectType::ReverseBoth,
+        ReconnectType::ReverseAll,
+        ReconnectType::Swap,
+        ReconnectType::SwapReverseFirst,
+        ReconnectType::SwapReverseSecond,
+    ];
+}
+
+/// Best of the seven 3-opt reconnections after cutting the edges leaving positions i < j < k,
+/// as (length saved, reconnection); the gain is negative when every option is longer. Segments
+/// are costed as if reversing them were free, i.e. the grid is taken to be symmetric.
+pub fn three_opt_gain(tour: &[usize], i: usize, j: usize, k: usize, grid: &[Vec<f32>]) -> (f32, ReconnectType) {
+    let n = tour.len();
+    let (a, b, c, d, e, f) = (tour[i], tour[i+1], tour[j], tour[j+1], tour[k], tour[(k + 1) % n]);
+    let removed = grid[a][b] + grid[c][d] + grid[e][f];
+
+    let mut best = (f32::NEG_INFINITY, ReconnectType::ReverseFirst);
+    for reconnect in ReconnectType::ALL {
+        let added = match reconnect {
+            ReconnectType::ReverseFirst => grid[a][c] + grid[b][d] + grid[e][f],
+            ReconnectType::ReverseSecond => grid[a][b] + grid[c][e] + grid[d][f],
+            ReconnectType::ReverseBoth => grid[a][c] + grid[b][e] + grid[d][f],
+            ReconnectType::ReverseAll => grid[a][e] + grid[d][c] + grid[b][f],
+            ReconnectType::Swap => grid[a][d] + grid[e][b] + grid[c][f],
+            ReconnectType::SwapReverseFirst => grid[a][d] + grid[e][c] + grid[b][f],
+            ReconnectType::SwapReverseSecond => grid[a][e] + grid[d][b] + grid[c][f],
+        };
+        if removed - added > best.0 {
+            best = (removed - added, reconnect);
+        }
+    }
+
+    best
+}
+
+/// The tour after cutting the edges leaving positions i < j < k and reconnecting as `reconnect`
+pub fn apply_three_opt(tour: &[usize], i: usize, j: usize, k: usize, reconnect: ReconnectType) -> Vec<usize> {
+    let first = &tour[i+1..=j];
+    let second = &tour[j+1..=k];
+    let reversed = |segment: &[usize]| segment.iter().rev().copied().collect::<Vec<usize>>();
+
+    let middle: Vec<usize> = match reconnect {
+        ReconnectType::ReverseFirst => [reversed(first), second.to_vec()].concat(),
+        ReconnectType::ReverseSecond => [first.to_vec(), reversed(second)].concat(),
+        ReconnectType::ReverseBoth => [reversed(first), reversed(second)].concat(),
+        ReconnectType::ReverseAll => [reversed(second), reversed(first)].concat(),
+        ReconnectType::Swap => [second, first].concat(),
+        ReconnectType::SwapReverseFirst => [second.to_vec(), reversed(first)].concat(),
+        ReconnectType::SwapReverseSecond => [reversed(second), first.to_vec()].concat(),
+    };
+
+    [&tour[..=i], &middle, &tour[k+1..]].concat()
+}
+
+/// Outcome of `iterated_local_search`
+pub struct IteratedSearchResult {
+    pub tour: Vec<usize>,
+    pub length: f32,
+    /// Kicks tried before stopping, at most the iteration cap
+    pub iterations: usize,
+}
+
+/// Iterated local search: 2-opt from `tour`, then up to `iterations` times kick the best tour with
+/// a random double bridge, 2-opt it again and keep it if it got shorter.
+///
+/// With a `target_gap`, e.g. 0.05 for 5%, the search stops as soon as the best tour is within that
+/// fraction of `one_tree_bound`, instead of always running every iteration.
+pub fn iterated_local_search<R: Rng>(
+    tour: Vec<usize>,
+    grid: &[Vec<f32>],
+    iterations: usize,
+    target_gap: Option<f32>,
+    rng: &mut R,
+) -> Result<IteratedSearchResult, TspError> {
+    let (mut best, mut best_length) = two_opt(tour, grid)?;
+    let bound = target_gap.map(|_| one_tree_bound(grid));
+    let reached_target = |length: f32| match (target_gap, bound) {
+        (Some(gap), Some(bound)) => length - bound <= gap * bound,
+        _ => false,
+    };
+
+    let mut done = 0;
+    // below four cities every tour is the same cycle, so there is nothing to kick
+    while done < iterations && best.len() >= 4 && !reached_target(best_length) {
+        done += 1;
+        let (tour, length) = two_opt(double_bridge(&best, rng), grid)?;
+        if length < best_length - EPSILON {
+            (best, best_length) = (tour, length);
+        }
+    }
+
+    Ok(IteratedSearchResult { tour: best, length: best_length, iterations: done })
+}
+
+/// Cuts the tour into four pieces after the first city, A B C D, and reconnects them as A C B D;
+/// 2-opt can't undo that in a single move
+fn double_bridge<R: Rng>(tour: &[usize], rng: &mut R) -> Vec<usize> {
+    let mut cuts = sample(rng, tour.len() - 1, 3).into_vec();
+    cuts.sort_unstable();
+    let (p1, p2, p3) = (cuts[0] + 1, cuts[1] + 1, cuts[2] + 1);
+
+    [&tour[..p1], &tour[p2..p3], &tour[p1..p2], &tour[p3..]].concat()
+}
+
+/// 3-opt local search: repeatedly applies the best reconnection of any three cut edges that
+/// shortens the tour, until none does. Only cuts next to one of a city's `THREE_OPT_NEIGHBOURS`
+/// nearest cities are tried, since improving moves almost always bring close cities together.
+/// The first city stays in place.
+pub fn three_opt(mut tour: Vec<usize>, grid: &[Vec<f32>]) -> Result<(Vec<usize>, f32), TspError> {
+    validate_grid(grid)?;
+    validate_tour(&tour, grid.len())?;
+
+    let n = tour.len();
+    if n < 6 {
+        // fewer cities than three non-adjacent edges: 2-opt already covers every move
+        return two_opt(tour, grid);
+    }
+    let neighbours = k_nearest(grid, THREE_OPT_NEIGHBOURS);
+
+    let mut improved = true;
+    while improved {
+        improved = false;
+        let mut position = vec![0; n];
+        for (p, &city) in tour.iter().enumerate() {
+            position[city] = p;
+        }
+        // cut positions next to a city: the edges into and out of it
+        let cuts_at = |city: usize| [position[city], (position[city] + n - 1) % n];
+
+        'scan: for i in 0..n {
+            for &x in &neighbours[tour[i]] {
+                for &y in &neighbours[tour[(i + 1) % n]] {
+                    for j in cuts_at(x) {
+                        for k in cuts_at(y) {
+                            let mut cuts = [i, j, k];
+                            cuts.sort_unstable();
+                            let [i, j, k] = cuts;
+                            if i == j || j == k {
+                                continue;
+                            }
+                            let (gain, reconnect) = three_opt_gain(&tour, i, j, k, grid);
+                            if gain > EPSILON {
+                                tour = apply_three_opt(&tour, i, j, k, reconnect);
+                                improved = true;
+                                break 'scan;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let length = tour_length(grid, &tour)?;
+    Ok((tour, length))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::calculate_edge_grid;
+    use crate::point::{generate_points_with, Point};
+    use crate::tour::random_tour;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn every_improvement_is_reported() {
+        // a bowtie over the corners of a square, which one move uncrosses
+        let corners = [(10.0, 10.0), (90.0, 10.0), (10.0, 90.0), (90.0, 90.0)];
+        let grid = calculate_edge_grid(corners.iter().map(|&(x, y)| Point { x, y, label: None }).collect()).unwrap();
+
+        let mut steps: Vec<(Vec<usize>, f32)> = Vec::new();
+        let record = |tour: &[usize], length: f32| steps.push((tour.to_vec(), length));
+        let result = two_opt_with_improvements(vec![0, 1, 2, 3], &grid, record).unwrap();
+        assert_eq!(result, (vec![0, 1, 3, 2], 320.0));
+        assert_eq!(steps, vec![result.clone()]);
+        assert_eq!(two_opt(vec![0, 1, 2, 3], &grid).unwrap(), result);
+        assert!(two_opt(vec![0, 1, 2], &grid).is_err());
+    }
+
+    #[test]
+    fn improvements_shrink_to_the_final_tour() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let points = (0..30)
+            .map(|_| Point { x: rng.gen_range(0.0..100.0), y: rng.gen_range(0.0..100.0), label: None })
+            .collect();
+        let grid = calculate_edge_grid(points).unwrap();
+        let start: Vec<usize> = (0..30).collect();
+
+        let improvements: Vec<(Vec<usize>, f32)> = two_opt_improvements(start.clone(), &grid).unwrap().collect();
+        assert!(improvements.len() > 1);
+        assert!(improvements.windows(2).all(|pair| pair[1].1 < pair[0].1));
+        let (tour, length) = two_opt(start, &grid).unwrap();
+        assert_eq!(improvements.last().unwrap().0, tour);
+        assert!((improvements.last().unwrap().1 - length).abs() < 1e-2);
+        // a 2-optimal start has nothing to yield
+        assert_eq!(two_opt_improvements(tour, &grid).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn improvement_is_reported_against_the_start() {
+        let mut rng = StdRng::seed_from_u64(8);
+        let grid = calculate_edge_grid(generate_points_with(20, 100, false, &mut rng).unwrap()).unwrap();
+        // the cities are placed at random, so visiting them in numbered order is a random tour
+        let start: Vec<usize> = (0..20).collect();
+
+        let result = local_search(start.clone(), &grid).unwrap();
+        assert_eq!(result.initial_length, tour_length(&grid, &start).unwrap());
+        assert!(result.final_length <= result.initial_length);
+        let expected = (result.initial_length - result.final_length) / result.initial_length * 100.0;
+        assert_eq!(result.improvement_percent(), expected);
+        assert!(expected > 0.0);
+
+        let unchanged = LocalSearchResult { tour: start, initial_length: 0.0, final_length: 0.0 };
+        assert_eq!(unchanged.improvement_percent(), 0.0);
+    }
+
+    #[test]
+    fn generous_target_gap_stops_early() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let grid = calculate_edge_grid(generate_points_with(30, 100, false, &mut rng).unwrap()).unwrap();
+        let start: Vec<usize> = (0..30).collect();
+
+        let full = iterated_local_search(start.clone(), &grid, 200, None, &mut StdRng::seed_from_u64(1)).unwrap();
+        assert_eq!(full.iterations, 200);
+        assert!(full.length <= two_opt(start.clone(), &grid).unwrap().1);
+        assert!((tour_length(&grid, &full.tour).unwrap() - full.length).abs() < 1e-2);
+
+        let early = iterated_local_search(start, &grid, 200, Some(0.5), &mut StdRng::seed_from_u64(1)).unwrap();
+        assert!(early.iterations < 20, "ran {} iterations", early.iterations);
+        assert!(early.length <= 1.5 * one_tree_bound(&grid));
+    }
+
+    #[test]
+    fn double_bridge_keeps_every_city_and_the_start() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let tour: Vec<usize> = (0..10).collect();
+        for _ in 0..20 {
+            let kicked = double_bridge(&tour, &mut rng);
+            assert_eq!(kicked[0], 0);
+            validate_tour(&kicked, 10).unwrap();
+            assert_ne!(kicked, tour);
+        }
+    }
+
+    #[test]
+    fn uncrossing_a_bowtie_gives_the_square() {
+        let corners = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+        let points: Vec<Point> = corners.iter().map(|&(x, y)| Point { x, y, label: None }).collect();
+        assert_eq!(tour_crossings(&points, &[0, 2, 1, 3]).len(), 1);
+
+        let (tour, length) = uncross(vec![0, 2, 1, 3], &points).unwrap();
+        assert_eq!(tour, vec![0, 1, 2, 3]);
+        assert_eq!(length, 4.0);
+
+        let mut rng = StdRng::seed_from_u64(6);
+        let points = generate_points_with(30, 100, false, &mut rng).unwrap();
+        let (tour, _) = uncross(random_tour(30, &mut rng), &points).unwrap();
+        validate_tour(&tour, 30).unwrap();
+        assert!(tour_crossings(&points, &tour).is_empty());
+    }
+
+    #[test]
+    fn three_opt_gain_matches_recomputing_the_tour() {
+        let mut rng = StdRng::seed_from_u64(10);
+        let grid = calculate_edge_grid(generate_points_with(8, 100, false, &mut rng).unwrap()).unwrap();
+        let tour = random_tour(8, &mut rng);
+        let length = tour_length(&grid, &tour).unwrap();
+
+        for i in 0..8 {
+            for j in i + 1..8 {
+                for k in j + 1..8 {
+                    let saved = |reconnect| {
+                        length - tour_length(&grid, &apply_three_opt(&tour, i, j, k, reconnect)).unwrap()
+                    };
+                    let (gain, best) = three_opt_gain(&tour, i, j, k, &grid);
+                    assert!((gain - saved(best)).abs() < 1e-3, "cuts {i} {j} {k}");
+                    for reconnect in ReconnectType::ALL {
+                        assert!(saved(reconnect) <= gain + 1e-3, "cuts {i} {j} {k}, {reconnect:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn three_opt_gets_past_two_opt_local_optima() {
+        let mut improved = 0;
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let grid = calculate_edge_grid(generate_points_with(30, 100, false, &mut rng).unwrap()).unwrap();
+            let (stuck, stuck_length) = two_opt(random_tour(30, &mut rng), &grid).unwrap();
+
+            let (tour, length) = three_opt(stuck, &grid).unwrap();
+            validate_tour(&tour, 30).unwrap();
+            assert!((tour_length(&grid, &tour).unwrap() - length).abs() < 1e-2, "seed {seed}");
+            assert!(length <= stuck_length, "seed {seed}");
+            if length < stuck_length - 1e-3 {
+                improved += 1;
+            }
+        }
+        assert!(improved > 0);
+    }
+}
@@ -1,197 +1,661 @@
-use core::f32;
-use rand::Rng;
-use std::time::Instant;
+use log::{debug, log_enabled, Level};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use travelling_salesman::display::{
+    display_plot, display_tour, edge_lengths, format_grid, format_grid_heatmap, format_histogram, format_labeled_path,
+    format_path, format_plot, Animation, ProgressBar,
+};
+use travelling_salesman::difficulty::{difficulty, format_difficulty};
+use travelling_salesman::selfcheck::{format_gap_table, selfcheck};
+use travelling_salesman::solve::BRUTE_FORCE_CITIES;
+use travelling_salesman::*;
 
 const CITY_COUNT: usize = 6;
-const MAP_WIDTH: u16 = 100;
+/// Pause between `--animate` frames unless `--delay-ms` says otherwise
+const ANIMATION_DELAY_MS: u64 = 50;
+/// Whole-number coordinates, as in the original generator
+const INTEGER_COORDINATES: bool = false;
+/// City the printed tours start from; `None` keeps each solver's own starting city
+const DEPOT: Option<usize> = None;
 
 const SHOW_PLOT: bool = true;
-
-const IS_DEBUG: bool = true;
-const MEASURE_TIMING: bool = true;
-const SHOW_ALL_TRAVERSALS: bool = false;
-const GRAPH_PIXELS: usize = 50;
+/// Brute force estimated to run longer than this needs `--force`
+const MAX_ESTIMATED_SECONDS: u64 = 60;
+/// Random tours sampled for the length histogram logged at debug level
+const RANDOM_TOUR_SAMPLES: usize = 1000;
+/// Complete tours between checkpoint saves
+const CHECKPOINT_EVERY: u64 = 1_000_000;
+/// Width and height in pixels of the `--output-dir` tour.svg
+const SVG_VIEWPORT: (f32, f32) = (500.0, 500.0);
+const SVG_STROKE_WIDTH: f32 = 1.0;
+const SVG_CITY_RADIUS: f32 = 3.0;
 
 // https://tspvis.com/
 // https://www.routific.com/
 // https://www.math.uwaterloo.ca/tsp/app/diy.html?
 // https://getcircuit.com/
 // https://www.optaplanner.org/
+//
+// Diagnostics go through `log`, so verbosity is picked at runtime, e.g.
+// `RUST_LOG=debug` for the grid and each new minimum, `RUST_LOG=trace` for every traversal.
+//
+// Usage:
+//   travelling_salesman [solve] [--stdin] [--format plain|json|csv|matrix] [--metric euclidean|manhattan|haversine]
+//                       [--algorithm NAME|list] [--force] [--unicode] [--animate [--delay-ms N]]
+//                       [--checkpoint FILE [--checkpoint-every N]] [--resume FILE]
+//                       [--seed N] [--output-dir DIR] [--warm-tour FILE] [--config FILE]
+//       solve a random instance, or `x y [name]` lines from stdin; a checkpointed brute force
+//       can be picked up again with `--resume`. `--output-dir` also writes plot.txt, tour.svg,
+//       result.json, grid.txt and heatmap.txt, under DIR/seed-N when seeded. `--warm-tour` starts
+//       branch-and-bound, two-opt or three-opt from the tour in FILE, e.g. a previous answer.
+//       `--config` reads these settings from a JSON file such as {"algorithm": "two-opt", "seed": 7};
+//       flags on the command line take precedence
+//   travelling_salesman bench [--algorithm NAME] [--cities N] [--trials N] [--seed N] [--report FILE] [--csv FILE]
+//       mean solve time of each solver over seeded random instances; `--report` also writes
+//       a Markdown report of every solver's tour on the first instance, and `--csv` appends
+//       each solver's mean length and time to a CSV for tracking scaling over runs
+//   travelling_salesman verify --tour "0 2 1 3" [--metric NAME]
+//       check a tour over the `x y` lines on stdin and print its length
+//   travelling_salesman generate [--cities N] [--seed N] [--integer]
+//       print a random instance as `x y` lines, ready for `--stdin`
+//   travelling_salesman snapshot [--seed N] [--cities N] [--file FILE] | --check FILE
+//       pin a seeded instance and its optimal tour to a fixtures file (default
+//       fixtures/seed-N.json), or re-run one and fail if anything changed
+//   travelling_salesman repl [--file FILE | --cities N --seed N] [--unicode]
+//       explore an instance interactively: solve, twoopt, length, show, add, remove; `help` lists them
+//   travelling_salesman selfcheck [--cities N] [--trials N] [--seed N]
+//       compare every heuristic against the exact optimum
+//   travelling_salesman difficulty [--cities N] [--trials N] [--seed N]
+//       solve many seeded instances exactly and report how hard they were
+//
+// `--animate` also runs 2-opt from the cities in numbered order, redrawing the tour after each
+// move it makes and pausing N ms (default 50) between frames, when stdout is a terminal.
 fn main() {
-    let points = generate_points(CITY_COUNT, MAP_WIDTH);
-    let grid = calculate_edge_grid(points);
-    let min_total_dist = brute_force(grid);
+    env_logger::init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match find_command(&args) {
+        Ok(((_, run, _), rest)) => run(rest),
+        Err(e) => Err(e.into()),
+    };
 
-    println!("Optimal path length: {min_total_dist}");
+    if let Err(e) = result {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
 }
 
-// (n-1)!/2
-fn brute_force(grid: Vec<Vec<f32>>) -> f32 {
-    let n = grid.len();
+type Command = fn(&[String]) -> Result<(), Box<dyn Error>>;
+
+/// A subcommand's name, what runs it and the flags it accepts
+type Subcommand = (&'static str, Command, &'static [&'static str]);
+
+/// Subcommands by name; with no name (or a leading flag) `solve` runs
+const COMMANDS: &[Subcommand] = &[
+    ("solve", solve, SOLVE_FLAGS),
+    ("bench", run_bench, BENCH_FLAGS),
+    ("verify", run_verify, VERIFY_FLAGS),
+    ("generate", run_generate, GENERATE_FLAGS),
+    ("snapshot", run_snapshot, SNAPSHOT_FLAGS),
+    ("repl", run_repl, REPL_FLAGS),
+    ("selfcheck", run_selfcheck, SELFCHECK_FLAGS),
+    ("difficulty", run_difficulty, DIFFICULTY_FLAGS),
+];
+
+const SOLVE_FLAGS: &[&str] = &[
+    "stdin", "format", "metric", "algorithm", "force", "unicode", "checkpoint", "checkpoint-every", "resume", "seed",
+    "output-dir", "animate", "delay-ms", "warm-tour", "config",
+];
+const BENCH_FLAGS: &[&str] = &["algorithm", "cities", "trials", "seed", "report", "csv"];
+const VERIFY_FLAGS: &[&str] = &["tour", "metric"];
+const GENERATE_FLAGS: &[&str] = &["cities", "seed", "integer"];
+const SNAPSHOT_FLAGS: &[&str] = &["seed", "cities", "file", "check"];
+const REPL_FLAGS: &[&str] = &["file", "cities", "seed", "unicode"];
+const SELFCHECK_FLAGS: &[&str] = &["cities", "trials", "seed"];
+const DIFFICULTY_FLAGS: &[&str] = &["cities", "trials", "seed"];
+
+fn command_names() -> Vec<&'static str> {
+    COMMANDS.iter().map(|(name, _, _)| *name).collect()
+}
+
+/// The subcommand `args` start with, and the arguments left for it
+fn find_command(args: &[String]) -> Result<(&'static Subcommand, &[String]), String> {
+    match args.first().map(String::as_str) {
+        None => Ok((&COMMANDS[0], args)),
+        Some(flag) if flag.starts_with("--") => Ok((&COMMANDS[0], args)),
+        Some(command) => match COMMANDS.iter().find(|(name, _, _)| *name == command) {
+            Some(entry) => Ok((entry, &args[1..])),
+            None => Err(format!("unknown command `{command}`; available: {}", command_names().join(", "))),
+        },
+    }
+}
+
+fn solve(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut flags = parse_flags(args, SOLVE_FLAGS)?;
+    if let Some(file) = flags.get("config") {
+        let config = SolveConfig::load(Path::new(file)).map_err(|e| format!("cannot load config `{file}`: {e}"))?;
+        merge_config(&mut flags, &config);
+    }
+    let animate = flags.contains_key("animate");
+    let delay = flag_or(&flags, "delay-ms", ANIMATION_DELAY_MS)?;
+    let format = flag_or(&flags, "format", OutputFormat::Plain)?;
+    let metric = flag_or(&flags, "metric", Metric::Euclidean)?;
+    // only plain output mixes in the plot, heuristics and timing; json/csv print just the result
+    let human = format == OutputFormat::Plain;
+
+    let solver = match flags.get("algorithm").map(String::as_str) {
+        Some("list") => {
+            println!("{}", solver_names().join("\n"));
+            return Ok(());
+        }
+        Some(name) => Some(find_solver(name).ok_or_else(|| {
+            format!("unknown algorithm `{name}`; available: {}", solver_names().join(", "))
+        })?),
+        None => None,
+    };
+    let exhaustive = matches!(flags.get("algorithm").map(String::as_str), None | Some("brute-force"));
+    let every = flag_or(&flags, "checkpoint-every", CHECKPOINT_EVERY)?;
+    let checkpoint = flags.get("checkpoint").map(PathBuf::from);
+    let seed: Option<u64> = flags.get("seed").map(|_| flag_or(&flags, "seed", 0)).transpose()?;
+    let warm_tour = match flags.get("warm-tour") {
+        Some(file) if !matches!(flags.get("algorithm").map(String::as_str), Some("branch-and-bound" | "two-opt" | "three-opt")) => {
+            return Err(format!("--warm-tour {file} needs --algorithm branch-and-bound, two-opt or three-opt").into());
+        }
+        Some(file) => {
+            let text = fs::read_to_string(file).map_err(|e| format!("cannot read `{file}`: {e}"))?;
+            Some(parse_tour(&text, "--warm-tour")?)
+        }
+        None => None,
+    };
+
+    if let Some(resume) = flags.get("resume") {
+        // the instance comes from the checkpoint; keep saving to it unless told otherwise
+        let resume = PathBuf::from(resume);
+        let saved = Checkpoint::load(&resume)?;
+        let start: Instant = Instant::now();
+        let (min_path, min_total_dist) = resume_brute_force(saved, checkpoint.as_ref().unwrap_or(&resume), every)?;
+        if MEASURE_TIMING && human {
+            println!("Took {:?}", start.elapsed());
+        }
+        write_result(&mut std::io::stdout().lock(), format, &[], &for_display(min_path), min_total_dist, metric.unit())?;
+        return Ok(());
+    }
+
+    let points = if flags.contains_key("stdin") {
+        read_points(std::io::stdin().lock())?
+    } else if let Some(seed) = seed {
+        generate_points_with(CITY_COUNT, MAP_WIDTH, INTEGER_COORDINATES, &mut StdRng::seed_from_u64(seed))?
+    } else {
+        generate_points(CITY_COUNT, MAP_WIDTH, INTEGER_COORDINATES)?
+    };
+    let style = if flags.contains_key("unicode") { PlotStyle::Unicode } else { PlotStyle::Ascii };
+    if SHOW_PLOT && human {
+        display_plot(&points, None, style);
+    }
+    let grid = calculate_edge_grid_metric(&points, metric)?;
+    let violations = triangle_violations(&grid);
+    if let Some((i, j, k)) = violations.first() {
+        eprintln!(
+            "warning: {} triples break the triangle inequality (e.g. {i} > {j} > {k} is shorter than {i} > {k}); heuristic results may be poor",
+            violations.len()
+        );
+    }
+
+    if animate && human {
+        let animation = Animation::new(Duration::from_millis(delay));
+        let frame = |tour: &[usize], length: f32| animation.frame(&points, tour, length, style);
+        let (tour, length) = two_opt_with_improvements((0..points.len()).collect(), &grid, frame)?;
+        display_tour(&points, &tour, length, style);
+        println!("2-opt path length: {length}");
+    }
+
+    if human {
+        report_heuristics(&grid)?;
+    }
+
+    // what runs when the hull shortcut doesn't apply, and the one search long enough to need a progress bar
+    let plain_brute_force = exhaustive && (solver.is_some() || metric != Metric::Euclidean);
+    // `solve_auto` only brute-forces small instances; bigger ones go to Held-Karp or branch and bound
+    let runs_brute_force =
+        plain_brute_force || exhaustive && (checkpoint.is_some() || grid.len() <= BRUTE_FORCE_CITIES);
+    if runs_brute_force {
+        let estimate = estimate_brute_force_time(grid.len());
+        if human {
+            println!("Estimated brute force time: {estimate:?}");
+        }
+        if estimate > Duration::from_secs(MAX_ESTIMATED_SECONDS) && !flags.contains_key("force") {
+            return Err(format!(
+                "brute force on {} cities is estimated at {estimate:?}, over the {MAX_ESTIMATED_SECONDS}s limit; pass --force to run anyway",
+                grid.len()
+            ).into());
+        }
+    }
 
     let start: Instant = Instant::now();
-    let min = traverse(vec![0], (1..n).collect(), f32::MAX, &grid);
-    if MEASURE_TIMING {
+    let (min_path, min_total_dist) = match (solver, &checkpoint, warm_tour) {
+        (_, Some(path), _) if exhaustive => brute_force_checkpointed(&grid, path, every)?,
+        (_, Some(_), _) => return Err("--checkpoint only applies to brute force".into()),
+        // the warm tour is the starting point; each solver rejects one that doesn't fit the instance
+        (_, None, Some(warm)) => match flags.get("algorithm").map(String::as_str) {
+            Some("branch-and-bound") => branch_and_bound_warm(&grid, &warm)?,
+            Some("three-opt") => three_opt(warm, &grid)?,
+            _ => two_opt(warm, &grid)?,
+        },
+        (_, None, None) if plain_brute_force && human => {
+            let bar = ProgressBar::new();
+            let solved = brute_force_with_progress(&grid, |fraction| bar.update(fraction));
+            bar.finish();
+            solved?
+        }
+        (Some(solver), None, None) => solver(&grid)?,
+        // the hull shortcut only holds for straight-line distances
+        (None, None, None) if metric == Metric::Euclidean => solve_auto(&points, &grid)?,
+        (None, None, None) => brute_force(&grid)?,
+    };
+    if MEASURE_TIMING && human {
         let duration = start.elapsed();
         println!("Took {duration:?}");
     }
+    // an optimal Euclidean tour never crosses itself
+    if exhaustive && metric == Metric::Euclidean && !tour_crossings(&points, &min_path).is_empty() {
+        eprintln!("warning: the optimal tour has crossing edges, so the distances or the solver are off");
+    }
+    let min_path = for_display(min_path);
+    let (from, to, longest) = bottleneck_edge(&min_path, &grid);
+    debug!("Longest leg: {from} > {to}, {longest:.1} {}", metric.unit());
+
+    write_result(&mut std::io::stdout().lock(), format, &points, &min_path, min_total_dist, metric.unit())?;
+
+    if let Some(dir) = flags.get("output-dir") {
+        let dir = match seed {
+            Some(seed) => Path::new(dir).join(format!("seed-{seed}")),
+            None => PathBuf::from(dir),
+        };
+        write_artifacts(&dir, &points, &grid, &min_path, min_total_dist, metric.unit(), style)?;
+    }
 
-    min
+    Ok(())
 }
 
-fn traverse(visited: Vec<usize>, pending: Vec<usize>, mut min: f32, grid: &Vec<Vec<f32>>) -> f32 {
-    if !pending.is_empty() {
-        // permutation
-        for i in 0..pending.len() {
-            let mut future_visit = visited.clone();
-            future_visit.push(pending[i]);
-            
-            let future_available: Vec<usize> = pending.iter().filter(|x| **x != pending[i]).map(|x| x.to_owned()).collect();
-            let local_min = traverse(future_visit, future_available, min, &grid);
-            if local_min < min {
-                min = local_min
-            }
-        }
+/// Every export for one solve, with fixed file names inside `dir`, which is created if missing
+fn write_artifacts(
+    dir: &Path,
+    points: &[Point],
+    grid: &[Vec<f32>],
+    tour: &[usize],
+    length: f32,
+    unit: Unit,
+    style: PlotStyle,
+) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(dir).map_err(|e| format!("cannot create output directory `{}`: {e}", dir.display()))?;
 
-        min
-    } else {
-        let n = visited.len();
-        let first_ix = visited[0];
-        let last_ix = visited[n-1];
-        let mut total_distance = grid[first_ix][last_ix];
-        for i in 0..n-1 {
-            let a_city = visited[i];
-            let b_city = visited[i+1];
-            total_distance += grid[a_city][b_city];
-        }
-
-        if IS_DEBUG && SHOW_ALL_TRAVERSALS {
-            display_path(&visited);
-        }
-        if total_distance < min {
-            min = total_distance;
-
-            if IS_DEBUG {
-                if !SHOW_ALL_TRAVERSALS {
-                    display_path(&visited);
-                }
-                println!("\t\t\tNew min: {min}");
-            }
-        }
+    let mut svg = Vec::new();
+    write_svg(&mut svg, points, tour, SVG_VIEWPORT, SVG_STROKE_WIDTH, SVG_CITY_RADIUS)?;
+    let mut json = Vec::new();
+    write_result(&mut json, OutputFormat::Json, points, tour, length, unit)?;
+    let artifacts = [
+        ("plot.txt", format_plot(points, Some(length), style).into_bytes()),
+        ("tour.svg", svg),
+        ("result.json", json),
+        ("grid.txt", format_grid(grid).into_bytes()),
+        ("heatmap.txt", format_grid_heatmap(grid).into_bytes()),
+    ];
+    for (name, contents) in artifacts {
+        let path = dir.join(name);
+        fs::write(&path, contents).map_err(|e| format!("cannot write `{}`: {e}", path.display()))?;
+    }
+
+    Ok(())
+}
 
-        min
+fn report_heuristics(grid: &[Vec<f32>]) -> Result<(), Box<dyn Error>> {
+    let (nn_path, nn_dist) = nearest_neighbor_multistart(grid, 0..grid.len())?;
+    let polished = local_search(nn_path.clone(), grid)?;
+    let nn_path = for_display(nn_path);
+    debug!("Nearest neighbor {}", format_path(&nn_path));
+    println!("Nearest neighbor path length: {nn_dist}");
+    debug!("Nearest neighbor + 2-opt {}", format_path(&for_display(polished.tour.clone())));
+    println!(
+        "Nearest neighbor + 2-opt path length: {} ({:.1}% shorter)",
+        polished.final_length, polished.improvement_percent()
+    );
+
+    let (ci_path, ci_dist) = cheapest_insertion(grid)?;
+    let ci_path = for_display(ci_path);
+    debug!("Cheapest insertion {}", format_path(&ci_path));
+    println!("Cheapest insertion path length: {ci_dist}");
+
+    if log_enabled!(Level::Debug) {
+        let lengths = sample_tour_lengths(grid, RANDOM_TOUR_SAMPLES, 0)?;
+        debug!("Lengths of {RANDOM_TOUR_SAMPLES} random tours:\n{}", format_histogram(&lengths, 10));
+        debug!("Edge lengths:\n{}", format_histogram(&edge_lengths(grid), 10));
     }
+
+    Ok(())
 }
 
-/// Calculate distances between points, as a grid, where grid[i][j] is the ditsance from city i to city j
-fn calculate_edge_grid(points: Vec<Point>) -> Vec<Vec<f32>> {
-    let n = points.len();
-    let mut grid = vec![vec![0 as f32; n]; n];
+fn run_bench(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let flags = parse_flags(args, BENCH_FLAGS)?;
+    let cities = flag_or(&flags, "cities", 8)?;
+    let trials: u32 = flag_or(&flags, "trials", 10)?;
+    let seed = flag_or(&flags, "seed", 0)?;
+    let solvers: Vec<(&str, Solver)> = match flags.get("algorithm") {
+        Some(name) => vec![(name.as_str(), find_solver(name).ok_or_else(|| format!("unknown algorithm `{name}`"))?)],
+        None => solver_names().into_iter().filter_map(|name| find_solver(name).map(|solver| (name, solver))).collect(),
+    };
 
-    for i in 0..n {
-        for j in i+1..n {
-            let dx = points[i].x as i16 - points[j].x as i16;
-            let dy = points[i].y as i16 - points[j].y as i16;
-            grid[i][j] = ((dx * dx + dy * dy) as f32).sqrt();
-            grid[j][i] = grid[i][j];
+    // the same instances for every solver
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut instances = Vec::new();
+    for _ in 0..trials {
+        let points = generate_points_with(cities, MAP_WIDTH, INTEGER_COORDINATES, &mut rng)?;
+        let grid = calculate_edge_grid(points.clone())?;
+        instances.push((points, grid));
+    }
+
+    println!("Bench: {trials} instances of {cities} cities, seed {seed}\n");
+    let mut report = Vec::new();
+    for (name, solver) in solvers {
+        let start: Instant = Instant::now();
+        let mut first = None;
+        let mut total_length = 0.0;
+        for (_, grid) in &instances {
+            let solved = solver(grid)?;
+            total_length += solved.1;
+            first.get_or_insert(solved);
+        }
+        let mean = start.elapsed() / trials.max(1);
+        let elapsed = format!("{mean:?}");
+        println!("{}", format!("{name:<28} {elapsed:<12} {}", footprint(name, cities)).trim_end());
+        if let Some(path) = flags.get("csv") {
+            let row = TimingRow {
+                n: cities,
+                algorithm: name.to_owned(),
+                length: total_length / trials.max(1) as f32,
+                millis: mean.as_secs_f64() * 1000.0,
+                optimal: is_exact(name),
+            };
+            append_timing_csv(path, row).map_err(|e| format!("cannot append to `{path}`: {e}"))?;
+        }
+        if let Some((tour, length)) = first {
+            let optimal = is_exact(name);
+            report.push((name.to_owned(), TourResult { tour, length, time: mean, optimal }));
         }
     }
 
-    if IS_DEBUG {
-        display_grid(&grid);
+    if let (Some(path), Some((points, _))) = (flags.get("report"), instances.first()) {
+        write_markdown_report(points, &report, Path::new(path))?;
+        println!("\nReport on the first instance written to {path}");
     }
-    
-    grid
+
+    Ok(())
 }
 
-/// Generates dataset: (x, y) co-ordinates for n cities, in a space of area = width * width
-fn generate_points(n: usize, width: u16) -> Vec<Point> {
-    let mut points = vec![Point { x: 0, y: 0}; n];
+/// What an exact solver's memory grows with; the searches and heuristics keep only a path and a
+/// tour or two
+fn footprint(name: &str, cities: usize) -> String {
+    match name {
+        "held-karp" => format!("tables {}", format_bytes(held_karp_table_bytes(cities, false))),
+        _ => String::new(),
+    }
+}
 
-    for i in 0..n {
-        let xi: u16 = rand::thread_rng().gen_range(0..width);
-        let yi: u16 = rand::thread_rng().gen_range(0..width);
-        points[i].x = xi;
-        points[i].y = yi;
+fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
     }
+    if unit == 0 { format!("{bytes} B") } else { format!("{size:.1} {}", UNITS[unit]) }
+}
+
+fn run_verify(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let flags = parse_flags(args, VERIFY_FLAGS)?;
+    let metric = flag_or(&flags, "metric", Metric::Euclidean)?;
+    let tour = flags.get("tour").ok_or("verify needs `--tour`, e.g. --tour \"0 2 1 3\"")?;
+    let tour = parse_tour(tour, "--tour")?;
+
+    let points = read_points(std::io::stdin().lock())?;
+    let grid = calculate_edge_grid_metric(&points, metric)?;
+    let length = tour_length(&grid, &tour)?;
+    println!("Valid {}", format_labeled_path(&tour, &points));
+    println!("Path length: {length:.1} {}", metric.unit());
 
-    if IS_DEBUG || SHOW_PLOT {
-        display_plot(&points);
+    Ok(())
+}
+
+/// City numbers separated by spaces, commas or newlines; `source` names where they came from
+fn parse_tour(text: &str, source: &str) -> Result<Vec<usize>, String> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|city| !city.is_empty())
+        .map(|city| city.parse().map_err(|_| format!("invalid city `{city}` in `{source}`")))
+        .collect()
+}
+
+fn run_generate(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let flags = parse_flags(args, GENERATE_FLAGS)?;
+    let cities = flag_or(&flags, "cities", CITY_COUNT)?;
+    let integer = flags.contains_key("integer");
+    let points = match flags.get("seed") {
+        Some(_) => generate_points_with(cities, MAP_WIDTH, integer, &mut StdRng::seed_from_u64(flag_or(&flags, "seed", 0)?))?,
+        None => generate_points(cities, MAP_WIDTH, integer)?,
+    };
+
+    for point in points {
+        println!("{} {}", point.x, point.y);
     }
 
-    points
+    Ok(())
 }
 
-fn display_grid(grid: &Vec<Vec<f32>>) {
-    let n = grid.len();
-    println!("\nGrid:");
+fn run_snapshot(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let flags = parse_flags(args, SNAPSHOT_FLAGS)?;
 
-    print!("\n");
-    print!("    ");
-    for j in 0..n {
-        print!(" {:>5}", j);
+    if let Some(file) = flags.get("check") {
+        let differences = Snapshot::load(Path::new(file))?.check()?;
+        if !differences.is_empty() {
+            return Err(format!("snapshot `{file}` no longer matches: {}", differences.join("; ")).into());
+        }
+        println!("Snapshot `{file}` matches");
+        return Ok(());
     }
-    print!("\n");
-    print!("    ");
-    for _ in 0..n {
-        print!("______");
+
+    let seed = flag_or(&flags, "seed", 0)?;
+    let cities = flag_or(&flags, "cities", CITY_COUNT)?;
+    let file = flags.get("file").map_or_else(|| Path::new("fixtures").join(format!("seed-{seed}.json")), PathBuf::from);
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
     }
-    print!("\n");
+    let snapshot = Snapshot::take(seed, cities)?;
+    snapshot.save(&file)?;
+    println!("Wrote {} (optimal length {})", file.display(), snapshot.length);
+
+    Ok(())
+}
 
-    for i in 0..n {
-        print!("{:^3} |", i);
+fn run_repl(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let flags = parse_flags(args, REPL_FLAGS)?;
+    let points = match flags.get("file") {
+        Some(file) => read_points(std::io::BufReader::new(fs::File::open(file)?))?,
+        None => {
+            let cities = flag_or(&flags, "cities", CITY_COUNT)?;
+            let seed = flag_or(&flags, "seed", 0)?;
+            generate_points_with(cities, MAP_WIDTH, INTEGER_COORDINATES, &mut StdRng::seed_from_u64(seed))?
+        }
+    };
+    let style = if flags.contains_key("unicode") { PlotStyle::Unicode } else { PlotStyle::Ascii };
+    let mut repl = Repl::new(Problem::new(points)?, style);
+    println!("{} cities loaded; type `help` for the commands", repl.problem().points().len());
 
-        for j in 0..n {
-            print!("{0:>5.1} ", grid[i][j]);
+    let mut line = String::new();
+    loop {
+        print!("> ");
+        std::io::stdout().flush()?;
+        line.clear();
+        if std::io::stdin().read_line(&mut line)? == 0 {
+            break;
+        }
+        match line.parse::<ReplCommand>() {
+            Ok(ReplCommand::Quit) => break,
+            Ok(command) => match repl.execute(&command) {
+                Ok(output) => println!("{output}"),
+                Err(e) => println!("error: {e}"),
+            },
+            Err(e) => println!("{e}"),
         }
-        print!("\n");
     }
-    print!("\n");
+
+    Ok(())
 }
 
-fn display_plot(points: &Vec<Point>) {
-    let n = points.len();
-    let mut plot = vec![vec!["  ".to_owned(); GRAPH_PIXELS]; GRAPH_PIXELS];
-    let scale_factor = (MAP_WIDTH / GRAPH_PIXELS as u16) as usize;
+fn run_selfcheck(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let flags = parse_flags(args, SELFCHECK_FLAGS)?;
+    let cities = flag_or(&flags, "cities", 8)?;
+    let trials = flag_or(&flags, "trials", 100)?;
+    let seed = flag_or(&flags, "seed", 0)?;
 
-    // points
-    for i in 0..n {
-        let ix = (points[i].x/scale_factor as u16) as usize;
-        let iy = (points[i].y/scale_factor as u16) as usize;
-        println!("City {i}: ({}, {})        ({}, {}) ", points[i].x, points[i].y, ix, iy);
-        plot[ix][iy] = format!("{i:>2}");
-    }
+    let summaries = selfcheck(cities, trials, seed)?;
+    println!("Self-check: {trials} instances of {cities} cities, seed {seed}\n");
+    print!("{}", format_gap_table(&summaries));
+
+    Ok(())
+}
+
+fn run_difficulty(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let flags = parse_flags(args, DIFFICULTY_FLAGS)?;
+    let cities = flag_or(&flags, "cities", 10)?;
+    let trials = flag_or(&flags, "trials", 50)?;
+    let seed = flag_or(&flags, "seed", 0)?;
+
+    let results = difficulty(cities, trials, seed)?;
+    println!("Difficulty: {trials} instances of {cities} cities, seeds {seed}..{}\n", seed + trials as u64);
+    print!("{}", format_difficulty(&results));
+
+    Ok(())
+}
 
-    // plot
-    println!("\nPlot:");
-    print!("x");
-    for _ in 0..GRAPH_PIXELS {
-        print!("--");
+fn for_display(tour: Vec<usize>) -> Vec<usize> {
+    match DEPOT {
+        Some(depot) => rotate_to(&tour, depot),
+        None => tour,
     }
-    print!("x\n");
-    for j in 0..GRAPH_PIXELS {
-        print!("|");
-        for i in 0..GRAPH_PIXELS {
-            print!("{}", plot[i][GRAPH_PIXELS-1-j]);
+}
+
+/// Collects `--name value` pairs; a flag with no value after it (`--stdin`) maps to "true".
+/// Flags not in `known` are rejected, so a typo doesn't silently fall back to a default.
+fn parse_flags(args: &[String], known: &[&str]) -> Result<HashMap<String, String>, String> {
+    let mut flags = HashMap::new();
+    let mut args = args.iter().peekable();
+    while let Some(arg) = args.next() {
+        let name = arg.strip_prefix("--").ok_or_else(|| format!("unexpected argument `{arg}`"))?;
+        if !known.contains(&name) {
+            let known: Vec<String> = known.iter().map(|flag| format!("--{flag}")).collect();
+            return Err(format!("unknown option `{arg}`; expected one of {}", known.join(", ")));
         }
-        print!("|\n");
+        let value = match args.peek() {
+            Some(value) if !value.starts_with("--") => args.next().unwrap().to_owned(),
+            _ => "true".to_owned(),
+        };
+        flags.insert(name.to_owned(), value);
     }
-    print!("x");
-    for _ in 0..GRAPH_PIXELS {
-        print!("--");
+
+    Ok(flags)
+}
+
+/// Fills in the settings from `config` that no command-line flag already gave
+fn merge_config(flags: &mut HashMap<String, String>, config: &SolveConfig) {
+    for (name, value) in config.flags() {
+        flags.entry(name.to_owned()).or_insert(value);
     }
-    print!("x\n");
 }
 
-fn display_path(path: &Vec<usize>) {
-    let pattern: Vec<String> = path.iter().map(|x| x.to_string()).collect();
-    let pattern = pattern.join(" > ");
-    // dbg!(pattern);
-    println!("path: {pattern} > {}", path[0]);
+fn flag_or<T: FromStr>(flags: &HashMap<String, String>, name: &str, default: T) -> Result<T, String> {
+    match flags.get(name) {
+        Some(value) => value.parse().map_err(|_| format!("invalid value `{value}` for `--{name}`")),
+        None => Ok(default),
+    }
 }
 
-#[derive(Clone)]
-struct Point {
-    x: u16,
-    y: u16
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_dir_gets_every_artifact() {
+        let points = generate_points_with(5, 100, false, &mut StdRng::seed_from_u64(1)).unwrap();
+        let grid = calculate_edge_grid(points.clone()).unwrap();
+        let (tour, length) = brute_force(&grid).unwrap();
+
+        let root = std::env::temp_dir().join(format!("tsp-artifacts-{}", std::process::id()));
+        let dir = root.join("seed-1");
+        write_artifacts(&dir, &points, &grid, &tour, length, Unit::MapUnits, PlotStyle::Ascii).unwrap();
+
+        for name in ["plot.txt", "tour.svg", "result.json", "grid.txt", "heatmap.txt"] {
+            let contents = fs::read_to_string(dir.join(name)).unwrap();
+            assert!(!contents.is_empty(), "{name} is empty");
+        }
+        assert!(fs::read_to_string(dir.join("tour.svg")).unwrap().starts_with("<svg"));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn commands_are_found_by_name_and_solve_is_the_default() {
+        let args = |line: &str| -> Vec<String> { line.split_whitespace().map(str::to_owned).collect() };
+        let found = |line: &str| find_command(&args(line)).map(|((name, _, _), rest)| (*name, rest.to_vec()));
+
+        assert_eq!(found("").unwrap(), ("solve", vec![]));
+        assert_eq!(found("--seed 3").unwrap(), ("solve", args("--seed 3")));
+        assert_eq!(found("solve --seed 3").unwrap(), ("solve", args("--seed 3")));
+        assert_eq!(found("verify --tour 0").unwrap(), ("verify", args("--tour 0")));
+        for name in command_names() {
+            assert_eq!(found(name).unwrap().0, name);
+        }
+
+        let error = found("solv --seed 3").unwrap_err();
+        assert!(error.starts_with("unknown command `solv`; available: solve, bench"), "{error}");
+    }
+
+    #[test]
+    fn every_subcommand_parses_each_flag_it_accepts() {
+        for &(name, _, known) in COMMANDS {
+            let mut args = Vec::new();
+            for flag in known {
+                args.extend([format!("--{flag}"), "1".to_owned()]);
+            }
+            let flags = parse_flags(&args, known).unwrap_or_else(|e| panic!("{name}: {e}"));
+            assert_eq!(flags.len(), known.len(), "{name} lists a flag twice");
+            assert!(known.iter().all(|flag| flags[*flag] == "1"), "{name}");
+        }
+    }
+
+    #[test]
+    fn unknown_flags_are_rejected_with_the_valid_ones() {
+        let args = vec!["--sed".to_owned(), "3".to_owned()];
+        let error = parse_flags(&args, GENERATE_FLAGS).unwrap_err();
+        assert_eq!(error, "unknown option `--sed`; expected one of --cities, --seed, --integer");
+    }
+
+    #[test]
+    fn command_line_flags_override_the_config_file() {
+        let algorithm = Some("two-opt".to_owned());
+        let config = SolveConfig { algorithm, seed: Some(7), unicode: true, ..Default::default() };
+        let args: Vec<String> = ["--seed", "9", "--format", "json"].iter().map(|arg| arg.to_string()).collect();
+        let mut flags = parse_flags(&args, &["seed", "format", "algorithm", "unicode"]).unwrap();
+        merge_config(&mut flags, &config);
+
+        assert_eq!(flags["algorithm"], "two-opt");
+        assert_eq!(flags["seed"], "9");
+        assert_eq!(flags["format"], "json");
+        assert_eq!(flags["unicode"], "true");
+        assert_eq!(flags.len(), 4);
+    }
 }
@@ -0,0 +1,110 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::TspError;
+use crate::grid::calculate_edge_grid_with;
+use crate::point::Point;
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// How the distance between two cities is measured
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Metric {
+    /// Straight-line distance on the map
+    Euclidean,
+    /// Distance along axis-aligned streets: |dx| + |dy|
+    Manhattan,
+    /// Great-circle distance, reading `x` as longitude and `y` as latitude in degrees
+    Haversine,
+}
+
+/// What the lengths a metric produces are measured in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Unit {
+    /// Unitless map coordinates
+    MapUnits,
+    Kilometers,
+}
+
+impl Metric {
+    pub fn unit(self) -> Unit {
+        match self {
+            Metric::Euclidean | Metric::Manhattan => Unit::MapUnits,
+            Metric::Haversine => Unit::Kilometers,
+        }
+    }
+
+    pub fn distance(self, a: &Point, b: &Point) -> f32 {
+        match self {
+            Metric::Euclidean => a.distance(b),
+            Metric::Manhattan => {
+                let delta = a - b;
+                (delta.x.abs() + delta.y.abs()) as f32
+            }
+            Metric::Haversine => haversine(a, b),
+        }
+    }
+}
+
+impl FromStr for Metric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "euclidean" => Ok(Metric::Euclidean),
+            "manhattan" => Ok(Metric::Manhattan),
+            "haversine" => Ok(Metric::Haversine),
+            _ => Err(format!("unknown metric `{s}`, expected euclidean, manhattan or haversine")),
+        }
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Unit::MapUnits => write!(f, "units"),
+            Unit::Kilometers => write!(f, "km"),
+        }
+    }
+}
+
+/// `calculate_edge_grid` for any built-in metric
+pub fn calculate_edge_grid_metric(points: &[Point], metric: Metric) -> Result<Vec<Vec<f32>>, TspError> {
+    if points.is_empty() {
+        return Err(TspError::EmptyInput);
+    }
+
+    Ok(calculate_edge_grid_with(points, |a, b| metric.distance(a, b)))
+}
+
+fn haversine(a: &Point, b: &Point) -> f32 {
+    let (lat_a, lat_b) = (a.y.to_radians(), b.y.to_radians());
+    let d_lat = lat_b - lat_a;
+    let d_lon = (b.x - a.x).to_radians();
+
+    let h = (d_lat / 2.0).sin().powi(2) + lat_a.cos() * lat_b.cos() * (d_lon / 2.0).sin().powi(2);
+    (2.0 * EARTH_RADIUS_KM * h.sqrt().min(1.0).asin()) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::{write_result, OutputFormat};
+
+    fn reported(metric: Metric) -> String {
+        let mut out = Vec::new();
+        write_result(&mut out, OutputFormat::Plain, &[], &[0, 1], 2.0, metric.unit()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn haversine_lengths_are_in_km() {
+        assert!(reported(Metric::Haversine).ends_with("length: 2.0 km\n"));
+        assert!(reported(Metric::Euclidean).ends_with("length: 2.0 units\n"));
+        assert_eq!(Metric::Manhattan.unit(), Unit::MapUnits);
+
+        // a degree of latitude is about 111.2 km
+        let (a, b) = (Point { x: 13.4, y: 52.0, label: None }, Point { x: 13.4, y: 53.0, label: None });
+        assert!((Metric::Haversine.distance(&a, &b) - 111.19).abs() < 0.01);
+    }
+}
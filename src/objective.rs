@@ -0,0 +1,179 @@
+use crate::geometry::{tour_axis_changes, tour_turn_angles};
+use crate::point::Point;
+
+/// What a complete tour is scored by
+#[derive(Clone, Debug, PartialEq)]
+pub enum Objective {
+    /// Length of the closed tour, plus any service times (a constant for a full tour)
+    TotalDistance,
+    /// Sum of the arrival times at every city after the start: how long customers wait in total.
+    /// Each stop's service time delays every arrival after it, so the order matters.
+    MinimumLatency,
+    /// Tour length plus `lambda` per degree turned (see `tour_turn_angles`), for smoother routes.
+    /// Turns depend on where the cities are, so their `points` come along.
+    TurnPenalty { points: Vec<Point>, lambda: f32 },
+    /// Tour length plus `penalty` per switch between the x and y axes (see `tour_axis_changes`), for
+    /// Manhattan layouts like warehouse aisles where turning into another aisle is what's slow
+    DirectionChanges { points: Vec<Point>, penalty: f32 },
+    /// Path length with the edge back to the start charged as `ReturnPolicy` says, plus any
+    /// service times
+    Return(ReturnPolicy),
+}
+
+/// What the closing edge, from the last city back to the first, costs
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReturnPolicy {
+    /// Its distance in the grid, as for any other edge
+    Full,
+    /// A fixed fee whatever the distance, e.g. dropping off a one-way rental
+    Flat(f32),
+    /// Nothing: the tour is an open path ending wherever its last city is
+    None,
+}
+
+/// Soft cap on how many stops a route makes, the depot not counted: each one past `max_stops`
+/// costs `over_penalty`. A full tour always makes n - 1 stops, so this only matters where stops
+/// can be left out, e.g. `choose_stops`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StopLimit {
+    pub max_stops: Option<usize>,
+    pub over_penalty: f32,
+}
+
+impl StopLimit {
+    /// Charge for making `stops` stops
+    pub fn penalty(&self, stops: usize) -> f32 {
+        match self.max_stops {
+            Some(max_stops) if stops > max_stops => (stops - max_stops) as f32 * self.over_penalty,
+            _ => 0.0,
+        }
+    }
+}
+
+impl Objective {
+    /// Whether a tour and its mirror image always score the same. An open or flat-fee return
+    /// doesn't: mirroring a tour that starts at city 0 changes which edges the path uses.
+    pub fn is_reversible(&self) -> bool {
+        matches!(
+            self,
+            Objective::TotalDistance
+                | Objective::TurnPenalty { .. }
+                | Objective::DirectionChanges { .. }
+                | Objective::Return(ReturnPolicy::Full)
+        )
+    }
+}
+
+/// Cost of `tour` under `objective`. `service_times[i]` is how long a stop at city i takes; `None`
+/// means stops are instantaneous.
+pub fn path_cost(tour: &[usize], grid: &[Vec<f32>], objective: &Objective, service_times: Option<&[f32]>) -> f32 {
+    let n = tour.len();
+    let service = |city: usize| service_times.map_or(0.0, |s| s[city]);
+
+    match objective {
+        Objective::TotalDistance => {
+            let mut total = grid[tour[n-1]][tour[0]];
+            for i in 0..n-1 {
+                total += grid[tour[i]][tour[i+1]];
+            }
+            total + tour.iter().map(|c| service(*c)).sum::<f32>()
+        }
+        Objective::MinimumLatency => {
+            let mut clock = 0.0;
+            let mut latency = 0.0;
+            for i in 1..n {
+                clock += service(tour[i-1]) + grid[tour[i-1]][tour[i]];
+                latency += clock;
+            }
+            latency
+        }
+        Objective::TurnPenalty { points, lambda } => {
+            let turning: f32 = tour_turn_angles(points, tour).iter().sum();
+            path_cost(tour, grid, &Objective::TotalDistance, service_times) + lambda * turning
+        }
+        Objective::DirectionChanges { points, penalty } => {
+            let changes = tour_axis_changes(points, tour) as f32;
+            path_cost(tour, grid, &Objective::TotalDistance, service_times) + penalty * changes
+        }
+        Objective::Return(policy) => path_distance(tour, grid, *policy) + tour.iter().map(|c| service(*c)).sum::<f32>(),
+    }
+}
+
+/// Length of the path through `tour` in order, with the closing edge charged by `policy`
+pub fn path_distance(tour: &[usize], grid: &[Vec<f32>], policy: ReturnPolicy) -> f32 {
+    let n = tour.len();
+    let mut total = match policy {
+        ReturnPolicy::Full => grid[tour[n-1]][tour[0]],
+        ReturnPolicy::Flat(fee) => fee,
+        ReturnPolicy::None => 0.0,
+    };
+    for i in 0..n-1 {
+        total += grid[tour[i]][tour[i+1]];
+    }
+
+    total
+}
+
+/// Travel time of the closed tour when each edge's cost is scaled by `time_factor(t)`, t being the
+/// time already spent when the edge is started, e.g. doubling costs once rush hour begins.
+///
+/// Costs now depend on the order edges are driven in, so a tour and its mirror can differ, and
+/// heuristics that assume a fixed cost per edge (2-opt's deltas, the hull shortcut) no longer apply.
+pub fn time_dependent_cost(tour: &[usize], grid: &[Vec<f32>], time_factor: &dyn Fn(f32) -> f32) -> f32 {
+    let n = tour.len();
+    let mut clock = 0.0;
+    for i in 0..n {
+        clock += grid[tour[i]][tour[(i + 1) % n]] * time_factor(clock);
+    }
+
+    clock
+}
+
+/// When each city of `tour` is reached, as `(city, arrival)` pairs in visiting order: leaving the
+/// first city at `start_time` and driving each edge at `speed` distance units per time unit,
+/// after spending `service_times[city]` at every stop (see `path_cost`). The path is open, so
+/// there is no arrival back at the start, e.g. delivery ETAs.
+pub fn visit_schedule(tour: &[usize], grid: &[Vec<f32>], start_time: f32, speed: f32, service_times: Option<&[f32]>) -> Vec<(usize, f32)> {
+    let service = |city: usize| service_times.map_or(0.0, |s| s[city]);
+
+    let mut clock = start_time;
+    let mut schedule = Vec::with_capacity(tour.len());
+    for (i, &city) in tour.iter().enumerate() {
+        if i > 0 {
+            let previous = tour[i-1];
+            clock += service(previous) + grid[previous][city] / speed;
+        }
+        schedule.push((city, clock));
+    }
+
+    schedule
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One-way costs, so the closing edge 2 -> 0 (7) differs from 0 -> 2 (100)
+    fn one_way() -> Vec<Vec<f32>> {
+        vec![vec![0.0, 1.0, 100.0], vec![100.0, 0.0, 2.0], vec![7.0, 100.0, 0.0]]
+    }
+
+    #[test]
+    fn return_policy_prices_only_the_closing_edge() {
+        let grid = one_way();
+        assert_eq!(path_distance(&[0, 1, 2], &grid, ReturnPolicy::Full), 10.0);
+        assert_eq!(path_distance(&[0, 1, 2], &grid, ReturnPolicy::Flat(4.0)), 7.0);
+        assert_eq!(path_distance(&[0, 1, 2], &grid, ReturnPolicy::None), 3.0);
+        assert_eq!(path_distance(&[1, 2, 0], &grid, ReturnPolicy::None), 9.0);
+        assert_eq!(path_cost(&[0, 1, 2], &grid, &Objective::Return(ReturnPolicy::Flat(4.0)), Some(&[1.0; 3])), 10.0);
+    }
+
+    #[test]
+    fn schedule_adds_driving_and_service_time() {
+        let grid = one_way();
+        assert_eq!(visit_schedule(&[0, 1, 2], &grid, 8.0, 1.0, None), vec![(0, 8.0), (1, 9.0), (2, 11.0)]);
+        let service = [0.5, 2.0, 9.0];
+        assert_eq!(visit_schedule(&[0, 1, 2], &grid, 8.0, 2.0, Some(&service)), vec![(0, 8.0), (1, 9.0), (2, 12.0)]);
+        assert!(visit_schedule(&[], &grid, 8.0, 1.0, None).is_empty());
+    }
+}
@@ -0,0 +1,261 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::display::{city_name, format_labeled_path, map_square};
+use crate::metric::Unit;
+use crate::point::Point;
+use crate::tour::{optimality_gap, tour_to_matrix};
+
+/// How the final result is written to stdout
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    /// `Optimal path: ...` and `Optimal path length: ... <unit>` lines
+    Plain,
+    /// `{"length": ..., "unit": ..., "tour": [...]}`
+    Json,
+    /// `length,tour` with the tour's cities separated by spaces
+    Csv,
+    /// The tour's permutation matrix (see `tour_to_matrix`), one row of 0s and 1s per line
+    Matrix,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plain" => Ok(OutputFormat::Plain),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            "matrix" => Ok(OutputFormat::Matrix),
+            _ => Err(format!("unknown format `{s}`, expected plain, json, csv or matrix")),
+        }
+    }
+}
+
+/// Writes the tour and its length, which is measured in `unit`. Plain output names the cities of
+/// `points` that have labels; JSON adds the tour's names as `labels` when any city has one.
+/// `points` may be empty when only the grid is known, e.g. resuming from a checkpoint.
+pub fn write_result<W: Write>(w: &mut W, format: OutputFormat, points: &[Point], tour: &[usize], length: f32, unit: Unit) -> io::Result<()> {
+    let cities: Vec<String> = tour.iter().map(|x| x.to_string()).collect();
+    match format {
+        OutputFormat::Plain => {
+            writeln!(w, "Optimal {}", format_labeled_path(tour, points))?;
+            writeln!(w, "Optimal path length: {length:.1} {unit}")
+        }
+        OutputFormat::Json if points.iter().any(|p| p.label.is_some()) => {
+            let names: Vec<String> = tour.iter().map(|&c| city_name(points, c)).collect();
+            let labels = serde_json::to_string(&names).map_err(io::Error::from)?;
+            writeln!(w, "{{\"length\": {length}, \"unit\": \"{unit}\", \"tour\": [{}], \"labels\": {labels}}}", cities.join(", "))
+        }
+        OutputFormat::Json => writeln!(w, "{{\"length\": {length}, \"unit\": \"{unit}\", \"tour\": [{}]}}", cities.join(", ")),
+        OutputFormat::Csv => writeln!(w, "{length},{}", cities.join(" ")),
+        OutputFormat::Matrix => {
+            for row in tour_to_matrix(tour) {
+                let entries: Vec<String> = row.iter().map(|x| x.to_string()).collect();
+                writeln!(w, "{}", entries.join(" "))?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Writes the cities and the closed tour as an SVG of `viewport` (width, height) pixels, with the
+/// `map_square` stretched onto it and y increasing upwards as in the terminal plot. Positions keep
+/// full float precision, so close cities stay apart however large the figure.
+pub fn write_svg<W: Write>(w: &mut W, points: &[Point], tour: &[usize], viewport: (f32, f32), stroke_width: f32, city_radius: f32) -> io::Result<()> {
+    let (width, height) = (viewport.0 as f64, viewport.1 as f64);
+    let (x_min, y_min, side) = map_square(points);
+    let (x_scale, y_scale) = (width / side, height / side);
+    let pixel = |p: &Point| ((p.x - x_min) * x_scale, height - (p.y - y_min) * y_scale);
+
+    writeln!(w, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\">")?;
+    let route: Vec<String> = tour.iter().map(|&c| pixel(&points[c])).map(|(x, y)| format!("{x},{y}")).collect();
+    writeln!(w, "  <polygon points=\"{}\" fill=\"none\" stroke=\"black\" stroke-width=\"{stroke_width}\"/>", route.join(" "))?;
+    for (i, point) in points.iter().enumerate() {
+        let (x, y) = pixel(point);
+        writeln!(w, "  <circle cx=\"{x}\" cy=\"{y}\" r=\"{city_radius}\" fill=\"red\"><title>{}</title></circle>", title(points, i))?;
+    }
+    writeln!(w, "</svg>")
+}
+
+/// Hover text of a city in the SVG: `city 3`, or its label with XML's special characters escaped
+fn title(points: &[Point], i: usize) -> String {
+    match &points[i].label {
+        Some(label) => label.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;"),
+        None => format!("city {i}"),
+    }
+}
+
+/// One solver's answer on an instance, as gathered for a report
+#[derive(Clone, Debug)]
+pub struct TourResult {
+    pub tour: Vec<usize>,
+    pub length: f32,
+    pub time: Duration,
+    /// From an exact solver, so `length` is the proven optimum
+    pub optimal: bool,
+}
+
+/// Writes a Markdown report to `path`: an instance summary, a row per solver with its length and
+/// time, and the shortest tour. The gap column is only there when some result is proven optimal.
+pub fn write_markdown_report(points: &[Point], results: &[(String, TourResult)], path: &Path) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    let optimum = results.iter().filter(|(_, r)| r.optimal).map(|(_, r)| r.length).reduce(f32::min);
+
+    writeln!(w, "# Solve report\n")?;
+    writeln!(w, "## Instance\n")?;
+    writeln!(w, "| cities | x range | y range |")?;
+    writeln!(w, "|---|---|---|")?;
+    let range = |coordinate: fn(&Point) -> f64| {
+        let min = points.iter().map(coordinate).fold(f64::INFINITY, f64::min);
+        let max = points.iter().map(coordinate).fold(f64::NEG_INFINITY, f64::max);
+        if points.is_empty() { "-".to_owned() } else { format!("{min:.1} to {max:.1}") }
+    };
+    writeln!(w, "| {} | {} | {} |\n", points.len(), range(|p| p.x), range(|p| p.y))?;
+
+    writeln!(w, "## Solvers\n")?;
+    match optimum {
+        Some(_) => writeln!(w, "| solver | length | time | gap |\n|---|---|---|---|")?,
+        None => writeln!(w, "| solver | length | time |\n|---|---|---|")?,
+    }
+    for (name, result) in results {
+        write!(w, "| {name} | {:.1} | {:?} |", result.length, result.time)?;
+        match optimum {
+            Some(optimum) => writeln!(w, " {:.2}% |", optimality_gap(result.length, optimum) * 100.0)?,
+            None => writeln!(w)?,
+        }
+    }
+
+    if let Some((name, best)) = results.iter().min_by(|(_, a), (_, b)| a.length.total_cmp(&b.length)) {
+        writeln!(w, "\n## Best tour\n")?;
+        writeln!(w, "{:.1} by {name}{}:\n", best.length, if best.optimal { ", proven optimal" } else { "" })?;
+        writeln!(w, "```\n{}\n```", format_labeled_path(&best.tour, points))?;
+    }
+
+    w.flush()
+}
+
+/// One line of the timing CSV: a solver's mean length and time at one instance size
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimingRow {
+    pub n: usize,
+    pub algorithm: String,
+    pub length: f32,
+    pub millis: f64,
+    pub optimal: bool,
+}
+
+const TIMING_HEADER: &str = "n,algorithm,length,millis,optimal";
+
+/// Appends `row` to the CSV at `path`, first creating it with a `n,algorithm,length,millis,optimal`
+/// header if it doesn't exist yet, so repeated bench runs build up one file of scaling data
+pub fn append_timing_csv(path: &str, row: TimingRow) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    if file.metadata()?.len() == 0 {
+        writeln!(file, "{TIMING_HEADER}")?;
+    }
+
+    writeln!(file, "{},{},{},{},{}", row.n, row.algorithm, row.length, row.millis, row.optimal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::read_points;
+
+    fn written(format: OutputFormat, points: &[Point], tour: &[usize]) -> String {
+        let mut out = Vec::new();
+        write_result(&mut out, format, points, tour, 12.5, Unit::MapUnits).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn each_format_writes_the_result() {
+        let tour = [0, 2, 1];
+        let plain = "Optimal path: 0 > 2 > 1 > 0\nOptimal path length: 12.5 units\n";
+        assert_eq!(written(OutputFormat::Plain, &[], &tour), plain);
+        let json = "{\"length\": 12.5, \"unit\": \"units\", \"tour\": [0, 2, 1]}\n";
+        assert_eq!(written(OutputFormat::Json, &[], &tour), json);
+        assert_eq!(written(OutputFormat::Csv, &[], &tour), "12.5,0 2 1\n");
+        assert_eq!(written(OutputFormat::Matrix, &[], &tour), "0 0 1\n1 0 0\n0 1 0\n");
+
+        for name in ["plain", "json", "csv", "matrix"] {
+            assert!(name.parse::<OutputFormat>().is_ok(), "{name}");
+        }
+        assert!("yaml".parse::<OutputFormat>().is_err());
+    }
+
+    #[test]
+    fn markdown_report_has_a_row_per_solver() {
+        let corners = [(0.0, 0.0), (4.0, 0.0), (4.0, 1.0)];
+        let points: Vec<Point> = corners.iter().map(|&(x, y)| Point { x, y, label: None }).collect();
+        let time = Duration::from_millis(3);
+        let result = |tour: Vec<usize>, length, optimal| TourResult { tour, length, time, optimal };
+        let results = [
+            ("nearest-neighbor".to_owned(), result(vec![0, 2, 1], 11.0, false)),
+            ("brute-force".to_owned(), result(vec![0, 1, 2], 10.0, true)),
+        ];
+
+        let path = std::env::temp_dir().join(format!("tsp-report-{}.md", std::process::id()));
+        write_markdown_report(&points, &results, &path).unwrap();
+        let report = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(report.starts_with("# Solve report\n"));
+        assert!(report.contains("| 3 | 0.0 to 4.0 | 0.0 to 1.0 |"));
+        assert!(report.contains("| solver | length | time | gap |\n|---|---|---|---|\n"));
+        assert!(report.contains("| nearest-neighbor | 11.0 | 3ms | 10.00% |\n"));
+        assert!(report.contains("| brute-force | 10.0 | 3ms | 0.00% |\n"));
+        assert!(report.contains("10.0 by brute-force, proven optimal:\n\n```\npath: 0 > 1 > 2 > 0\n```"));
+    }
+
+    #[test]
+    fn close_cities_get_distinct_svg_positions() {
+        let coordinates = [(0.0, 0.0), (100.0, 100.0), (50.0, 50.0), (50.001, 50.0)];
+        let points: Vec<Point> = coordinates.iter().map(|&(x, y)| Point { x, y, label: None }).collect();
+        let mut out = Vec::new();
+        write_svg(&mut out, &points, &[0, 2, 3, 1], (200.0, 100.0), 0.5, 2.0).unwrap();
+        let svg = String::from_utf8(out).unwrap();
+
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"200\" height=\"100\">"));
+        assert!(svg.contains("stroke-width=\"0.5\""));
+        let circles: Vec<&str> = svg.lines().filter(|line| line.contains("<circle")).collect();
+        assert_eq!(circles.len(), 4);
+        assert!(circles.iter().all(|circle| circle.contains("r=\"2\"")));
+        let centre = |circle: &str| circle.split('"').nth(1).unwrap().parse::<f64>().unwrap();
+        assert_eq!(centre(circles[0]), 0.0);
+        assert_eq!(centre(circles[1]), 200.0);
+        assert!(centre(circles[3]) > centre(circles[2]));
+    }
+
+    #[test]
+    fn labels_are_read_and_named_in_the_path() {
+        let points = read_points("0 0 Berlin Mitte\n3 4\n\n6,0,Paris\n".as_bytes()).unwrap();
+        assert_eq!(points[0].label.as_deref(), Some("Berlin Mitte"));
+        assert_eq!(points[1].label, None);
+
+        let plain = written(OutputFormat::Plain, &points, &[0, 1, 2]);
+        assert!(plain.starts_with("Optimal path: Berlin Mitte > 1 > Paris > Berlin Mitte\n"));
+        let json = written(OutputFormat::Json, &points, &[0, 1, 2]);
+        assert!(json.ends_with("\"tour\": [0, 1, 2], \"labels\": [\"Berlin Mitte\",\"1\",\"Paris\"]}\n"), "{json}");
+    }
+
+    #[test]
+    fn timing_csv_gets_one_header_then_a_row_per_append() {
+        let path = std::env::temp_dir().join(format!("tsp-timing-{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        let row = |algorithm: &str, millis| {
+            TimingRow { n: 8, algorithm: algorithm.to_owned(), length: 312.5, millis, optimal: true }
+        };
+        append_timing_csv(path, row("brute-force", 4.25)).unwrap();
+        append_timing_csv(path, row("held-karp", 0.5)).unwrap();
+
+        let csv = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(csv, format!("{TIMING_HEADER}\n8,brute-force,312.5,4.25,true\n8,held-karp,312.5,0.5,true\n"));
+    }
+}
@@ -0,0 +1,126 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::ops::Sub;
+
+use crate::error::TspError;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+    /// City name from the input, shown instead of the index when present
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+impl Point {
+    /// Straight-line distance, the Euclidean metric every default grid uses
+    pub fn distance(&self, other: &Point) -> f32 {
+        let delta = self - other;
+        (delta.x * delta.x + delta.y * delta.y).sqrt() as f32
+    }
+}
+
+/// `a - b` is the unlabelled vector from `b` to `a`
+impl Sub for &Point {
+    type Output = Point;
+
+    fn sub(self, other: &Point) -> Point {
+        Point { x: self.x - other.x, y: self.y - other.y, label: None }
+    }
+}
+
+/// Generates dataset: (x, y) co-ordinates for n cities, in a space of area = width * width.
+/// Coordinates are continuous in `[0, width)`; with `integer` they are whole numbers, as originally.
+pub fn generate_points(n: usize, width: u16, integer: bool) -> Result<Vec<Point>, TspError> {
+    generate_points_with(n, width, integer, &mut rand::thread_rng())
+}
+
+/// As `generate_points`, drawing from `rng` so a seeded generator gives a reproducible instance
+pub fn generate_points_with<R: Rng>(n: usize, width: u16, integer: bool, rng: &mut R) -> Result<Vec<Point>, TspError> {
+    if n == 0 || width == 0 {
+        return Err(TspError::EmptyInput);
+    }
+
+    let points = (0..n)
+        .map(|_| {
+            let (x, y) = if integer {
+                (rng.gen_range(0..width) as f64, rng.gen_range(0..width) as f64)
+            } else {
+                (rng.gen_range(0.0..width as f64), rng.gen_range(0.0..width as f64))
+            };
+            Point { x, y, label: None }
+        })
+        .collect();
+
+    Ok(points)
+}
+
+/// Regular `rows` x `cols` grid of cities `spacing` apart, numbered row by row from the origin,
+/// e.g. for modelling city blocks with the Manhattan metric
+pub fn generate_lattice_points(rows: usize, cols: usize, spacing: u16) -> Result<Vec<Point>, TspError> {
+    if rows == 0 || cols == 0 {
+        return Err(TspError::EmptyInput);
+    }
+
+    let mut points = Vec::with_capacity(rows * cols);
+    for r in 0..rows {
+        for c in 0..cols {
+            points.push(Point { x: (c * spacing as usize) as f64, y: (r * spacing as usize) as f64, label: None });
+        }
+    }
+
+    Ok(points)
+}
+
+/// Copy of `points` with each coordinate moved by up to `max_delta` either way, uniformly,
+/// reproducible from `seed`. Labels are kept. Coordinates aren't clamped to the map.
+pub fn perturb_points(points: &[Point], max_delta: u16, seed: u64) -> Vec<Point> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let delta = max_delta as f64;
+    points
+        .iter()
+        .map(|p| Point {
+            x: p.x + rng.gen_range(-delta..=delta),
+            y: p.y + rng.gen_range(-delta..=delta),
+            label: p.label.clone(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn continuous_coordinates_stay_on_the_map() {
+        let points = generate_points_with(200, 50, false, &mut StdRng::seed_from_u64(2)).unwrap();
+        assert!(points.iter().all(|p| (0.0..50.0).contains(&p.x) && (0.0..50.0).contains(&p.y)));
+        assert!(points.iter().any(|p| p.x.fract() != 0.0 || p.y.fract() != 0.0));
+
+        let whole = generate_points_with(200, 50, true, &mut StdRng::seed_from_u64(2)).unwrap();
+        assert!(whole.iter().all(|p| p.x.fract() == 0.0 && p.y.fract() == 0.0 && p.x < 50.0 && p.y < 50.0));
+    }
+
+    #[test]
+    fn lattice_is_numbered_row_by_row() {
+        let points = generate_lattice_points(2, 3, 10).unwrap();
+        let coordinates: Vec<(f64, f64)> = points.iter().map(|p| (p.x, p.y)).collect();
+        assert_eq!(coordinates, vec![(0.0, 0.0), (10.0, 0.0), (20.0, 0.0), (0.0, 10.0), (10.0, 10.0), (20.0, 10.0)]);
+
+        assert!(matches!(generate_lattice_points(0, 3, 10), Err(TspError::EmptyInput)));
+    }
+
+    #[test]
+    fn distance_and_difference_of_a_3_4_5_triangle() {
+        let a = Point { x: 1.0, y: 2.0, label: Some("A".to_owned()) };
+        let b = Point { x: 4.0, y: 6.0, label: None };
+        assert_eq!(a.distance(&b), 5.0);
+        assert_eq!(b.distance(&a), 5.0);
+        assert_eq!(a.distance(&a), 0.0);
+        assert_eq!(&b - &a, Point { x: 3.0, y: 4.0, label: None });
+    }
+}
@@ -0,0 +1,180 @@
+use std::cell::OnceCell;
+
+use crate::error::TspError;
+use crate::grid::{calculate_edge_grid, floyd_warshall};
+use crate::point::Point;
+use crate::solve::solve_auto;
+use crate::tour::validate_tour;
+
+/// An instance that can be edited in place: the cities and their distance grid are kept in step,
+/// so adding a city costs `O(n)` instead of rebuilding the whole `O(n²)` grid. Edges can also be
+/// set directly, e.g. to model a road network.
+pub struct Problem {
+    points: Vec<Point>,
+    grid: Vec<Vec<f32>>,
+    /// Some edge was set by hand, so the grid is no longer the cities' straight-line distances
+    edges_edited: bool,
+    /// `floyd_warshall` of the grid, computed on first use and dropped whenever the grid changes
+    shortest_paths: OnceCell<Vec<Vec<f32>>>,
+    /// Last solved tour, kept valid for the current city indices
+    tour: Option<Vec<usize>>,
+}
+
+impl Problem {
+    pub fn new(points: Vec<Point>) -> Result<Self, TspError> {
+        let grid = calculate_edge_grid(points.clone())?;
+        Ok(Problem { points, grid, edges_edited: false, shortest_paths: OnceCell::new(), tour: None })
+    }
+
+    pub fn points(&self) -> &Vec<Point> {
+        &self.points
+    }
+
+    pub fn grid(&self) -> &Vec<Vec<f32>> {
+        &self.grid
+    }
+
+    pub fn tour(&self) -> Option<&Vec<usize>> {
+        self.tour.as_ref()
+    }
+
+    /// Shortest route between every pair of cities over the grid's edges: the metric closure a
+    /// road network is solved on. Worked out once and reused until the grid next changes.
+    pub fn shortest_path_grid(&self) -> &Vec<Vec<f32>> {
+        self.shortest_paths.get_or_init(|| floyd_warshall(&self.grid))
+    }
+
+    /// Sets the direct edge from `i` to `j`, `f32::INFINITY` for no road. Later solves run on
+    /// `shortest_path_grid`, as the edges may no longer be shortest routes. The cached tour is kept;
+    /// only its length changes.
+    pub fn set_edge(&mut self, i: usize, j: usize, cost: f32) -> Result<(), TspError> {
+        let n = self.points.len();
+        if let Some(city) = [i, j].into_iter().find(|&city| city >= n) {
+            return Err(TspError::InvalidTour(format!("city {city} is out of range")));
+        }
+
+        self.grid[i][j] = cost;
+        self.edges_edited = true;
+        self.shortest_paths.take();
+        Ok(())
+    }
+
+    /// Solves the current instance and caches the tour. Once edges have been set by hand the
+    /// tour is over `shortest_path_grid`, and its legs may pass through other cities.
+    pub fn solve(&mut self) -> Result<(Vec<usize>, f32), TspError> {
+        let (tour, length) = if self.edges_edited {
+            // no points: the hull shortcut only holds for straight-line distances
+            solve_auto(&[], self.shortest_path_grid())?
+        } else {
+            solve_auto(&self.points, &self.grid)?
+        };
+        self.tour = Some(tour.clone());
+        Ok((tour, length))
+    }
+
+    /// Caches a tour found some other way, e.g. by a registry solver, if it fits this instance
+    pub fn set_tour(&mut self, tour: Vec<usize>) -> Result<(), TspError> {
+        validate_tour(&tour, self.points.len())?;
+        self.tour = Some(tour);
+        Ok(())
+    }
+
+    /// Appends a city as index `n`, extending every row by one column and adding its own row.
+    /// The cached tour doesn't visit the new city, so it is dropped.
+    pub fn add_point(&mut self, p: Point) {
+        let mut row: Vec<f32> = self.points.iter().map(|q| p.distance(q)).collect();
+        for (i, existing) in self.grid.iter_mut().enumerate() {
+            existing.push(self.points[i].distance(&p));
+        }
+
+        row.push(0.0);
+        self.grid.push(row);
+        self.points.push(p);
+        self.shortest_paths.take();
+        self.tour = None;
+    }
+
+    /// Drops city `i` with its row and column; cities after it shift down one index. A cached
+    /// tour skips the removed city and is renumbered to match, so it stays a valid tour.
+    pub fn remove_point(&mut self, i: usize) -> Result<Point, TspError> {
+        if i >= self.points.len() {
+            return Err(TspError::InvalidTour(format!("city {i} is out of range")));
+        }
+
+        self.grid.remove(i);
+        for row in self.grid.iter_mut() {
+            row.remove(i);
+        }
+        self.shortest_paths.take();
+        if let Some(tour) = self.tour.as_mut() {
+            tour.retain(|city| *city != i);
+            for city in tour.iter_mut() {
+                if *city > i {
+                    *city -= 1;
+                }
+            }
+        }
+
+        Ok(self.points.remove(i))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point::generate_points_with;
+    use crate::tour::validate_tour;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn cities(n: usize, seed: u64) -> Vec<Point> {
+        generate_points_with(n, 100, false, &mut StdRng::seed_from_u64(seed)).unwrap()
+    }
+
+    #[test]
+    fn added_cities_match_a_rebuilt_grid() {
+        let all = cities(9, 4);
+        let mut problem = Problem::new(all[..4].to_vec()).unwrap();
+        for point in &all[4..] {
+            problem.add_point(point.clone());
+        }
+
+        assert_eq!(problem.points(), &all);
+        assert_eq!(problem.grid(), &calculate_edge_grid(all).unwrap());
+    }
+
+    #[test]
+    fn removing_a_city_matches_a_rebuilt_grid() {
+        let all = cities(8, 6);
+        let mut problem = Problem::new(all.clone()).unwrap();
+        problem.solve().unwrap();
+
+        assert_eq!(problem.remove_point(3).unwrap(), all[3]);
+        let mut rest = all;
+        rest.remove(3);
+        assert_eq!(problem.grid(), &calculate_edge_grid(rest.clone()).unwrap());
+        validate_tour(problem.tour().unwrap(), rest.len()).unwrap();
+        // and it re-solves like the reduced instance from scratch
+        assert_eq!(problem.solve().unwrap(), Problem::new(rest).unwrap().solve().unwrap());
+        assert!(matches!(problem.remove_point(7), Err(TspError::InvalidTour(_))));
+    }
+
+    #[test]
+    fn editing_an_edge_refreshes_the_shortest_paths() {
+        let corners = [(0.0, 0.0), (3.0, 0.0), (3.0, 4.0), (0.0, 4.0)];
+        let mut problem = Problem::new(corners.iter().map(|&(x, y)| Point { x, y, label: None }).collect()).unwrap();
+        assert_eq!(problem.shortest_path_grid()[0][2], 5.0);
+
+        problem.set_edge(0, 2, f32::INFINITY).unwrap();
+        problem.set_edge(2, 0, f32::INFINITY).unwrap();
+        assert_eq!(problem.shortest_path_grid()[0][2], 7.0);
+        problem.set_edge(0, 1, 100.0).unwrap();
+        // the cached 0 -> 1 of 3 is gone; now it's quickest via 3
+        assert_eq!(problem.shortest_path_grid()[0][1], 9.0);
+        assert_eq!(problem.shortest_path_grid()[1][0], 3.0);
+
+        let (_, length) = problem.solve().unwrap();
+        assert_eq!(length, 14.0);
+        assert!(problem.set_edge(0, 4, 1.0).is_err());
+    }
+}
@@ -0,0 +1,81 @@
+use crate::brute_force::{branch_and_bound_anytime, brute_force};
+use crate::error::TspError;
+use crate::genetic::{genetic, GeneticConfig};
+use crate::held_karp::held_karp;
+use crate::heuristics::{cheapest_insertion, fragment_merge, nearest_neighbor, nearest_neighbor_multistart};
+use crate::local_search::{three_opt, two_opt};
+use crate::tour::rotate_to;
+
+/// Common shape of every grid-only solver: a tour starting at city 0 and its length
+pub type Solver = fn(&[Vec<f32>]) -> Result<(Vec<usize>, f32), TspError>;
+
+/// Solvers selectable by name, e.g. from `--algorithm`
+pub const SOLVERS: &[(&str, Solver)] = &[
+    ("brute-force", brute_force),
+    ("held-karp", |grid| held_karp(grid, false)),
+    ("branch-and-bound", |grid| branch_and_bound_anytime(grid, |_, _| {})),
+    ("nearest-neighbor", |grid| nearest_neighbor(grid, 0)),
+    ("nearest-neighbor-multistart", |grid| {
+        let (tour, length) = nearest_neighbor_multistart(grid, 0..grid.len())?;
+        Ok((rotate_to(&tour, 0), length))
+    }),
+    ("cheapest-insertion", cheapest_insertion),
+    ("fragment-merge", fragment_merge),
+    ("genetic", |grid| genetic(grid, &GeneticConfig::default())),
+    ("two-opt", |grid| {
+        let (tour, _) = nearest_neighbor_multistart(grid, 0..grid.len())?;
+        // the local searches keep the first city in place
+        let tour = rotate_to(&tour, 0);
+        two_opt(tour, grid)
+    }),
+    ("three-opt", |grid| {
+        let (tour, _) = nearest_neighbor_multistart(grid, 0..grid.len())?;
+        // the local searches keep the first city in place
+        let tour = rotate_to(&tour, 0);
+        three_opt(tour, grid)
+    }),
+];
+
+/// Solvers that prove their tour optimal
+const EXACT_SOLVERS: &[&str] = &["brute-force", "held-karp", "branch-and-bound"];
+
+pub fn is_exact(name: &str) -> bool {
+    EXACT_SOLVERS.contains(&name)
+}
+
+pub fn find_solver(name: &str) -> Option<Solver> {
+    SOLVERS.iter().find(|(solver_name, _)| *solver_name == name).map(|(_, solver)| *solver)
+}
+
+pub fn solver_names() -> Vec<&'static str> {
+    SOLVERS.iter().map(|(name, _)| *name).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::calculate_edge_grid;
+    use crate::point::generate_points_with;
+    use crate::tour::{tour_length, validate_tour};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn every_name_resolves_and_runs() {
+        let points = generate_points_with(8, 100, false, &mut StdRng::seed_from_u64(2)).unwrap();
+        let grid = calculate_edge_grid(points).unwrap();
+        let (_, optimum) = brute_force(&grid).unwrap();
+
+        for name in solver_names() {
+            let (tour, length) = find_solver(name).unwrap()(&grid).unwrap();
+            validate_tour(&tour, 8).unwrap();
+            assert_eq!(tour[0], 0, "{name}");
+            assert!((tour_length(&grid, &tour).unwrap() - length).abs() < 1e-3, "{name}");
+            assert!(length >= optimum - 1e-3, "{name}");
+            if is_exact(name) {
+                assert!((length - optimum).abs() < 1e-3, "{name}");
+            }
+        }
+        assert!(find_solver("simulated-annealing").is_none());
+    }
+}
@@ -0,0 +1,143 @@
+use std::str::FromStr;
+
+use crate::display::{format_labeled_path, format_plot, PlotStyle};
+use crate::error::TspError;
+use crate::local_search::two_opt;
+use crate::point::Point;
+use crate::problem::Problem;
+use crate::registry::{find_solver, solver_names};
+use crate::tour::tour_length;
+
+pub const HELP: &str = "\
+commands:
+  solve [ALGORITHM]  solve exactly, or with a registry solver (`nn` for nearest-neighbor)
+  twoopt             polish the current tour with 2-opt
+  length             length of the current tour
+  show               plot the cities
+  add X Y            add a city
+  remove I           remove city I
+  help               this list
+  quit               leave";
+
+/// One line typed into the REPL
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReplCommand {
+    Solve(Option<String>),
+    TwoOpt,
+    Length,
+    Show,
+    Add(Point),
+    Remove(usize),
+    Help,
+    Quit,
+}
+
+impl FromStr for ReplCommand {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let number = |word: &str| word.parse::<f64>().map_err(|_| format!("`{word}` is not a number"));
+        match words.as_slice() {
+            ["solve"] => Ok(ReplCommand::Solve(None)),
+            ["solve", name] => Ok(ReplCommand::Solve(Some(name.to_string()))),
+            ["twoopt"] => Ok(ReplCommand::TwoOpt),
+            ["length"] => Ok(ReplCommand::Length),
+            ["show"] => Ok(ReplCommand::Show),
+            ["add", x, y] => Ok(ReplCommand::Add(Point { x: number(x)?, y: number(y)?, label: None })),
+            ["remove", i] => Ok(ReplCommand::Remove(i.parse().map_err(|_| format!("`{i}` is not a city"))?)),
+            ["help"] => Ok(ReplCommand::Help),
+            ["quit"] | ["exit"] => Ok(ReplCommand::Quit),
+            [] => Err("type `help` for the commands".to_owned()),
+            [command, ..] => Err(format!("unknown or malformed command `{command}`; type `help` for the commands")),
+        }
+    }
+}
+
+/// An instance being explored one command at a time; each command returns what to print
+pub struct Repl {
+    problem: Problem,
+    style: PlotStyle,
+}
+
+impl Repl {
+    pub fn new(problem: Problem, style: PlotStyle) -> Self {
+        Repl { problem, style }
+    }
+
+    pub fn problem(&self) -> &Problem {
+        &self.problem
+    }
+
+    pub fn execute(&mut self, command: &ReplCommand) -> Result<String, TspError> {
+        match command {
+            ReplCommand::Solve(None) => {
+                let (tour, length) = self.problem.solve()?;
+                Ok(format!("{}\nlength: {length:.1}", format_labeled_path(&tour, self.problem.points())))
+            }
+            ReplCommand::Solve(Some(name)) => {
+                let canonical = if name == "nn" { "nearest-neighbor" } else { name.as_str() };
+                let Some(solver) = find_solver(canonical) else {
+                    return Ok(format!("unknown algorithm `{name}`; available: {}", solver_names().join(", ")));
+                };
+                let (tour, length) = solver(self.problem.grid())?;
+                self.problem.set_tour(tour.clone())?;
+                Ok(format!("{}\nlength: {length:.1}", format_labeled_path(&tour, self.problem.points())))
+            }
+            ReplCommand::TwoOpt => {
+                let Some(tour) = self.problem.tour().cloned() else {
+                    return Ok("no tour yet; `solve` first".to_owned());
+                };
+                let (tour, length) = two_opt(tour, self.problem.grid())?;
+                self.problem.set_tour(tour.clone())?;
+                Ok(format!("{}\nlength: {length:.1}", format_labeled_path(&tour, self.problem.points())))
+            }
+            ReplCommand::Length => match self.problem.tour() {
+                Some(tour) if !tour.is_empty() => Ok(format!("length: {:.1}", tour_length(self.problem.grid(), tour)?)),
+                _ => Ok("no tour yet; `solve` first".to_owned()),
+            },
+            ReplCommand::Show => Ok(format_plot(self.problem.points(), None, self.style)),
+            ReplCommand::Add(point) => {
+                self.problem.add_point(point.clone());
+                Ok(format!("added city {}", self.problem.points().len() - 1))
+            }
+            ReplCommand::Remove(i) => {
+                let point = self.problem.remove_point(*i)?;
+                Ok(format!("removed city {i} at ({:.1}, {:.1}); later cities moved down one", point.x, point.y))
+            }
+            ReplCommand::Help => Ok(HELP.to_owned()),
+            ReplCommand::Quit => Ok(String::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripted_session() {
+        let corners = [(0.0, 0.0), (3.0, 0.0), (3.0, 4.0), (0.0, 4.0)];
+        let problem = Problem::new(corners.iter().map(|&(x, y)| Point { x, y, label: None }).collect()).unwrap();
+        let mut repl = Repl::new(problem, PlotStyle::Ascii);
+        let mut run = |line: &str| repl.execute(&line.parse().unwrap()).unwrap();
+
+        assert_eq!(run("length"), "no tour yet; `solve` first");
+        assert_eq!(run("solve nn"), "path: 0 > 1 > 2 > 3 > 0\nlength: 14.0");
+        assert_eq!(run("add 1.5 -1"), "added city 4");
+        assert_eq!(run("length"), "no tour yet; `solve` first");
+        assert!(run("solve").ends_with("length: 14.6"));
+        assert_eq!(run("remove 4"), "removed city 4 at (1.5, -1.0); later cities moved down one");
+        assert_eq!(run("length"), "length: 14.0");
+        assert_eq!(run("help"), HELP);
+    }
+
+    #[test]
+    fn malformed_lines_are_rejected() {
+        assert_eq!("exit".parse(), Ok(ReplCommand::Quit));
+        assert_eq!("add 1 x".parse::<ReplCommand>(), Err("`x` is not a number".to_owned()));
+        assert!("fly 1 2".parse::<ReplCommand>().unwrap_err().starts_with("unknown or malformed command `fly`"));
+        assert!("remove".parse::<ReplCommand>().is_err());
+        assert!("".parse::<ReplCommand>().is_err());
+    }
+}
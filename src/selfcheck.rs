@@ -0,0 +1,64 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::brute_force::brute_force;
+use crate::error::TspError;
+use crate::grid::calculate_edge_grid;
+use crate::point::generate_points_with;
+use crate::registry::{is_exact, Solver, SOLVERS};
+use crate::tour::optimality_gap;
+use crate::MAP_WIDTH;
+
+/// Every registered solver except the exact ones, which would trivially score zero
+fn heuristics() -> Vec<(&'static str, Solver)> {
+    SOLVERS.iter().filter(|(name, _)| !is_exact(name)).cloned().collect()
+}
+
+/// How far one heuristic landed from the optimum over all trials, as fractions of the optimal length
+pub struct GapSummary {
+    pub name: &'static str,
+    pub mean_gap: f32,
+    pub worst_gap: f32,
+}
+
+/// Solves `trials` random instances of `cities` cities exactly and with every heuristic.
+/// The instances come from a generator seeded with `seed`, so a run can be repeated exactly.
+pub fn selfcheck(cities: usize, trials: usize, seed: u64) -> Result<Vec<GapSummary>, TspError> {
+    if trials == 0 {
+        return Err(TspError::EmptyInput);
+    }
+
+    let heuristics = heuristics();
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut summaries: Vec<GapSummary> = heuristics.iter()
+        .map(|(name, _)| GapSummary { name, mean_gap: 0.0, worst_gap: 0.0 })
+        .collect();
+
+    for _ in 0..trials {
+        let points = generate_points_with(cities, MAP_WIDTH, false, &mut rng)?;
+        let grid = calculate_edge_grid(points)?;
+        let (_, optimum) = brute_force(&grid)?;
+
+        for (summary, (_, heuristic)) in summaries.iter_mut().zip(heuristics.iter()) {
+            let (_, length) = heuristic(&grid)?;
+            // summing the same edges in another order can land a hair under the optimum
+            let gap = optimality_gap(length, optimum);
+            summary.mean_gap += gap / trials as f32;
+            summary.worst_gap = summary.worst_gap.max(gap);
+        }
+    }
+
+    Ok(summaries)
+}
+
+pub fn format_gap_table(summaries: &[GapSummary]) -> String {
+    let mut out = format!("{:<30} {:>10} {:>10}\n", "heuristic", "mean gap", "worst gap");
+    for summary in summaries {
+        out.push_str(&format!(
+            "{:<30} {:>9.2}% {:>9.2}%\n",
+            summary.name, summary.mean_gap * 100.0, summary.worst_gap * 100.0
+        ));
+    }
+
+    out
+}
@@ -0,0 +1,86 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::brute_force::brute_force;
+use crate::error::TspError;
+use crate::grid::calculate_edge_grid;
+use crate::point::{generate_points_with, Point};
+use crate::MAP_WIDTH;
+
+/// A seeded random instance pinned together with its optimal tour, to catch any later change to
+/// point generation, distances or tie-breaking
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub seed: u64,
+    pub points: Vec<Point>,
+    pub tour: Vec<usize>,
+    pub length: f32,
+}
+
+impl Snapshot {
+    /// Generates the instance for `seed` and solves it exactly
+    pub fn take(seed: u64, cities: usize) -> Result<Self, TspError> {
+        let points = generate_points_with(cities, MAP_WIDTH, false, &mut StdRng::seed_from_u64(seed))?;
+        let (tour, length) = brute_force(&calculate_edge_grid(points.clone())?)?;
+
+        Ok(Snapshot { seed, points, tour, length })
+    }
+
+    /// Pretty-printed JSON; floats are written in their shortest exact form, so they load back
+    /// bit for bit
+    pub fn save(&self, path: &Path) -> Result<(), TspError> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::from)?;
+        fs::write(path, json + "\n")?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, TspError> {
+        let json = fs::read_to_string(path)?;
+        let snapshot = serde_json::from_str(&json).map_err(io::Error::from)?;
+        Ok(snapshot)
+    }
+
+    /// Re-runs the snapshot's seed and lists every way the result now differs; empty when it
+    /// still matches exactly
+    pub fn check(&self) -> Result<Vec<String>, TspError> {
+        let now = Snapshot::take(self.seed, self.points.len())?;
+        let mut differences = Vec::new();
+        if now.points != self.points {
+            differences.push("generated points differ".to_owned());
+        }
+        if now.tour != self.tour {
+            differences.push(format!("tour is now {:?}, was {:?}", now.tour, self.tour));
+        }
+        if now.length.to_bits() != self.length.to_bits() {
+            differences.push(format!("length is now {}, was {}", now.length, self.length));
+        }
+
+        Ok(differences)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_snapshot_loads_back_and_still_matches() {
+        let path = std::env::temp_dir().join(format!("tsp-snapshot-{}.json", std::process::id()));
+        let snapshot = Snapshot::take(11, 6).unwrap();
+        snapshot.save(&path).unwrap();
+
+        let loaded = Snapshot::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, snapshot);
+        assert!(loaded.check().unwrap().is_empty());
+
+        let mut stale = loaded;
+        stale.tour.swap(1, 2);
+        stale.length += 1.0;
+        assert_eq!(stale.check().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,231 @@
+use log::debug;
+use std::fs;
+
+use crate::brute_force::{branch_and_bound_anytime, brute_force};
+use crate::error::TspError;
+use crate::geometry::{convex_hull, is_convex_position};
+use crate::grid::{calculate_edge_grid, validate_grid};
+use crate::held_karp::{self, held_karp, held_karp_table_bytes};
+use crate::point::{perturb_points, Point};
+use crate::tour::{canonical_tour, edge_overlap_percent, rotate_to, tour_length};
+
+/// Up to this many cities brute force is quicker than setting up Held-Karp's tables
+pub const BRUTE_FORCE_CITIES: usize = 9;
+/// Held-Karp's tables may take at most 1/`MEMORY_DIVISOR` of the available memory
+const MEMORY_DIVISOR: usize = 2;
+/// Memory assumed available when the system doesn't say
+const FALLBACK_MEMORY: usize = 1 << 30;
+
+/// Picks the cheapest exact method for the instance: the hull order when the cities are in convex
+/// position, brute force for a handful of cities, then Held-Karp as long as its tables fit in half
+/// the available memory, and branch and bound when they don't. `grid` must be the Euclidean grid
+/// of `points`.
+pub fn solve_auto(points: &[Point], grid: &[Vec<f32>]) -> Result<(Vec<usize>, f32), TspError> {
+    let budget = available_memory().unwrap_or(FALLBACK_MEMORY) / MEMORY_DIVISOR;
+    solve_auto_within(points, grid, budget)
+}
+
+/// As `solve_auto`, with Held-Karp limited to `memory_limit` bytes of tables instead of a share
+/// of what the system reports
+pub fn solve_auto_within(points: &[Point], grid: &[Vec<f32>], memory_limit: usize) -> Result<(Vec<usize>, f32), TspError> {
+    validate_grid(grid)?;
+    let n = grid.len();
+
+    if points.len() == n && is_convex_position(points) {
+        debug!("Cities are in convex position, using the hull order");
+        let tour = rotate_to(&convex_hull(points), 0);
+        let length = tour_length(grid, &tour)?;
+        return Ok((tour, length));
+    }
+    if n <= BRUTE_FORCE_CITIES {
+        return brute_force(grid);
+    }
+
+    if n <= held_karp::MAX_CITIES {
+        for low_memory in [false, true] {
+            let bytes = held_karp_table_bytes(n, low_memory);
+            if bytes <= memory_limit {
+                debug!("Held-Karp tables need {bytes} bytes of the {memory_limit} allowed");
+                return held_karp(grid, low_memory);
+            }
+        }
+    }
+    debug!("Held-Karp tables don't fit in {memory_limit} bytes, using branch and bound");
+    branch_and_bound_anytime(grid, |_, _| {})
+}
+
+/// Memory the system could give this process right now, in bytes: `MemAvailable` from
+/// `/proc/meminfo`, so `None` off Linux
+pub fn available_memory() -> Option<usize> {
+    let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find(|line| line.starts_with("MemAvailable:"))?;
+    let kib: usize = line.split_whitespace().nth(1)?.parse().ok()?;
+
+    Some(kib * 1024)
+}
+
+/// Best tour through just `cities` of the instance, e.g. 5 stops out of 20, in the original city
+/// numbers. With a `depot` that isn't among them it is added, and the tour starts there;
+/// otherwise it starts at `cities[0]`. The sub-instance is solved exactly by brute force.
+pub fn solve_subset(grid: &[Vec<f32>], cities: &[usize], depot: Option<usize>) -> Result<(Vec<usize>, f32), TspError> {
+    validate_grid(grid)?;
+
+    let mut subset: Vec<usize> = depot.into_iter().collect();
+    subset.extend(cities.iter().filter(|&&city| Some(city) != depot));
+    if subset.is_empty() {
+        return Err(TspError::EmptyInput);
+    }
+    let mut seen = vec![false; grid.len()];
+    for &city in &subset {
+        if city >= grid.len() {
+            return Err(TspError::InvalidTour(format!("city {city} is out of range")));
+        }
+        if seen[city] {
+            return Err(TspError::InvalidTour(format!("city {city} is listed more than once")));
+        }
+        seen[city] = true;
+    }
+
+    let sub_grid: Vec<Vec<f32>> = subset.iter().map(|&a| subset.iter().map(|&b| grid[a][b]).collect()).collect();
+    let (tour, length) = brute_force(&sub_grid)?;
+
+    Ok((tour.iter().map(|&i| subset[i]).collect(), length))
+}
+
+/// How robust the optimum is to coordinate noise: solves `points` and a copy jittered by
+/// `perturb_points(points, max_delta, seed)`, and returns the percentage of edges the two optimal
+/// tours share. Both are solved with `solve_auto` on Euclidean grids.
+pub fn solution_stability(points: &[Point], max_delta: u16, seed: u64) -> Result<f32, TspError> {
+    let (before, _) = solve_auto(points, &calculate_edge_grid(points.to_vec())?)?;
+    let jittered = perturb_points(points, max_delta, seed);
+    let (after, _) = solve_auto(&jittered, &calculate_edge_grid(jittered.clone())?)?;
+
+    Ok(edge_overlap_percent(&before, &after))
+}
+
+/// How much changing the grid, e.g. another metric or `preprocess`, moves the optimum: both
+/// grids solved with `solve_auto`, their tours in canonical form, and the percentage of edges
+/// the two share
+pub fn compare_optima(grid_a: &[Vec<f32>], grid_b: &[Vec<f32>]) -> Result<(Vec<usize>, Vec<usize>, f32), TspError> {
+    if grid_a.len() != grid_b.len() {
+        return Err(TspError::SizeMismatch(grid_a.len(), grid_b.len()));
+    }
+    let (a, _) = solve_auto(&[], grid_a)?;
+    let (b, _) = solve_auto(&[], grid_b)?;
+    let (a, b) = (canonical_tour(&a), canonical_tour(&b));
+    let overlap = edge_overlap_percent(&a, &b);
+
+    Ok((a, b, overlap))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::calculate_edge_grid;
+    use crate::point::generate_points_with;
+    use crate::tour::rotate_to;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// `n` cities evenly round a circle, numbered out of order: city i is `step` places round from
+    /// city i-1, with `step` coprime to `n`
+    fn circle(n: usize, step: usize) -> Vec<Point> {
+        (0..n)
+            .map(|i| {
+                let angle = (i * step % n) as f64 * std::f64::consts::TAU / n as f64;
+                Point { x: 50.0 + 40.0 * angle.cos(), y: 50.0 + 40.0 * angle.sin(), label: None }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cities_on_a_circle_take_the_hull_order() {
+        // far past what any exact search here could enumerate
+        let (n, step) = (200, 7);
+        let points = circle(n, step);
+        assert!(is_convex_position(&points));
+        let grid = calculate_edge_grid(points.clone()).unwrap();
+
+        let (tour, length) = solve_auto(&points, &grid).unwrap();
+        // round the circle is city 0, then the city one place on, and so on
+        let inverse = (1..n).find(|k| k * step % n == 1).unwrap();
+        let around: Vec<usize> = (0..n).map(|i| i * inverse % n).collect();
+        let mut backwards = around.clone();
+        backwards[1..].reverse();
+        let tour = rotate_to(&tour, 0);
+        assert!(tour == around || tour == backwards);
+        assert_eq!(length, tour_length(&grid, &around).unwrap());
+
+        let mut with_centre = points;
+        with_centre.push(Point { x: 50.0, y: 50.0, label: None });
+        assert!(!is_convex_position(&with_centre));
+    }
+
+    #[test]
+    fn subset_tour_uses_the_original_city_numbers() {
+        let grid = calculate_edge_grid(circle(10, 3)).unwrap();
+        let closed = |tour: &[usize]| (0..tour.len()).map(|i| grid[tour[i]][tour[(i + 1) % tour.len()]]).sum::<f32>();
+
+        let (tour, length) = solve_subset(&grid, &[7, 2, 5], None).unwrap();
+        assert_eq!(tour[0], 7);
+        let mut visited = tour.clone();
+        visited.sort_unstable();
+        assert_eq!(visited, vec![2, 5, 7]);
+        assert!((closed(&tour) - length).abs() <= length * 1e-5);
+
+        let (tour, length) = solve_subset(&grid, &[7, 2, 5], Some(0)).unwrap();
+        assert_eq!(tour[0], 0);
+        assert_eq!(tour.len(), 4);
+        assert!((closed(&tour) - length).abs() <= length * 1e-5);
+        assert_eq!(solve_subset(&grid, &[7, 2, 5], Some(2)).unwrap().0[0], 2);
+
+        assert!(matches!(solve_subset(&grid, &[7, 2, 7], None), Err(TspError::InvalidTour(_))));
+        assert!(matches!(solve_subset(&grid, &[], None), Err(TspError::EmptyInput)));
+    }
+
+    #[test]
+    fn unjittered_instance_is_fully_stable() {
+        let points = circle(9, 2);
+        assert_eq!(solution_stability(&points, 0, 1).unwrap(), 100.0);
+
+        let scrambled: Vec<f32> = (0..10).map(|seed| solution_stability(&points, 60, seed).unwrap()).collect();
+        assert!(scrambled.iter().all(|overlap| (0.0..=100.0).contains(overlap)));
+        assert!(scrambled.iter().any(|&overlap| overlap < 100.0));
+    }
+
+    #[test]
+    fn tight_memory_limit_falls_back_to_branch_and_bound() {
+        let points = generate_points_with(12, 100, false, &mut StdRng::seed_from_u64(21)).unwrap();
+        let grid = calculate_edge_grid(points).unwrap();
+        let (_, optimum) = held_karp(&grid, false).unwrap();
+
+        let full = held_karp_table_bytes(12, false);
+        let layered = held_karp_table_bytes(12, true);
+        assert_eq!(solve_auto_within(&[], &grid, full).unwrap(), held_karp(&grid, false).unwrap());
+        assert_eq!(solve_auto_within(&[], &grid, layered).unwrap(), held_karp(&grid, true).unwrap());
+        let bounded = branch_and_bound_anytime(&grid, |_, _| {}).unwrap();
+        assert_eq!(solve_auto_within(&[], &grid, layered - 1).unwrap(), bounded);
+        let (tour, length) = solve_auto_within(&[], &grid, 0).unwrap();
+        assert!((length - optimum).abs() <= optimum * 1e-5);
+        assert!((tour_length(&grid, &tour).unwrap() - length).abs() <= optimum * 1e-5);
+    }
+
+    #[test]
+    fn identical_grids_share_every_edge() {
+        let points = generate_points_with(8, 100, false, &mut StdRng::seed_from_u64(2)).unwrap();
+        let grid = calculate_edge_grid(points).unwrap();
+        let (a, b, overlap) = compare_optima(&grid, &grid).unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a, canonical_tour(&a));
+        assert_eq!(overlap, 100.0);
+
+        let mut toll = grid.clone();
+        for (i, j) in [(a[0], a[1]), (a[1], a[0])] {
+            toll[i][j] += 1000.0;
+        }
+        let (_, moved, overlap) = compare_optima(&grid, &toll).unwrap();
+        assert_ne!(moved, a);
+        assert!(overlap < 100.0);
+        assert!(matches!(compare_optima(&grid, &grid[..7]), Err(TspError::SizeMismatch(8, 7))));
+    }
+}
@@ -0,0 +1,146 @@
+use crate::point::Point;
+
+/// Uniform buckets over the cities' bounding box, so a nearest-neighbour query only looks at the
+/// cells around a city instead of every other city: about O(1) per query when cities are spread
+/// evenly, compared with the O(n) row scan of a distance grid.
+pub struct SpatialGrid<'a> {
+    points: &'a [Point],
+    cell_size: f64,
+    min_x: f64,
+    min_y: f64,
+    columns: usize,
+    rows: usize,
+    /// Cities in each cell, row by row
+    cells: Vec<Vec<usize>>,
+}
+
+/// Buckets `points` into square cells `cell_size` wide. Cells are stored densely, so the size
+/// should be near the typical spacing between cities, e.g. `default_cell_size`, which is also used
+/// when the size isn't positive. Cells are widened if need be so that no side has more of them
+/// than there are cities.
+pub fn build_spatial_grid(points: &[Point], cell_size: f64) -> SpatialGrid<'_> {
+    let mut cell_size = if cell_size > 0.0 && cell_size.is_finite() { cell_size } else { default_cell_size(points) };
+    let min_x = points.iter().map(|p| p.x).fold(f64::INFINITY, f64::min);
+    let min_y = points.iter().map(|p| p.y).fold(f64::INFINITY, f64::min);
+    let max_x = points.iter().map(|p| p.x).fold(f64::NEG_INFINITY, f64::max);
+    let max_y = points.iter().map(|p| p.y).fold(f64::NEG_INFINITY, f64::max);
+
+    let span = (max_x - min_x).max(max_y - min_y);
+    if span / cell_size > points.len() as f64 {
+        cell_size = span / points.len() as f64;
+    }
+
+    let mut grid = SpatialGrid { points, cell_size, min_x, min_y, columns: 1, rows: 1, cells: Vec::new() };
+    if !points.is_empty() {
+        grid.columns = ((max_x - min_x) / cell_size) as usize + 1;
+        grid.rows = ((max_y - min_y) / cell_size) as usize + 1;
+    }
+    grid.cells = vec![Vec::new(); grid.columns * grid.rows];
+    for (i, point) in points.iter().enumerate() {
+        let (column, row) = grid.cell_of(point);
+        grid.cells[row * grid.columns + column].push(i);
+    }
+
+    grid
+}
+
+/// Cell size giving about one city per cell over the bounding box
+pub fn default_cell_size(points: &[Point]) -> f64 {
+    let extent = |coordinate: fn(&Point) -> f64| {
+        let values = points.iter().map(coordinate);
+        values.clone().fold(f64::NEG_INFINITY, f64::max) - values.fold(f64::INFINITY, f64::min)
+    };
+    let (width, height, n) = (extent(|p| p.x), extent(|p| p.y), points.len() as f64);
+
+    let size = if width * height > 0.0 { (width * height / n).sqrt() } else { width.max(height) / n };
+    if size > 0.0 && size.is_finite() { size } else { 1.0 }
+}
+
+impl SpatialGrid<'_> {
+    /// The closest other city to city `i`; ties go to the lower-numbered city
+    pub fn nearest(&self, i: usize) -> Option<usize> {
+        self.k_nearest(i, 1).first().copied()
+    }
+
+    /// The `k` closest other cities to city `i`, nearest first, exactly as `grid::k_nearest` lists
+    /// them for a Euclidean grid
+    pub fn k_nearest(&self, i: usize, k: usize) -> Vec<usize> {
+        let point = &self.points[i];
+        let (column, row) = self.cell_of(point);
+
+        let mut found: Vec<(f32, usize)> = Vec::new();
+        for ring in 0..=self.columns.max(self.rows) {
+            for (c, r) in self.ring(column, row, ring) {
+                for &other in &self.cells[r * self.columns + c] {
+                    if other != i {
+                        found.push((point.distance(&self.points[other]), other));
+                    }
+                }
+            }
+            found.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+            found.truncate(k);
+            // anything in a later ring is at least `ring` whole cells away
+            if k == 0 || found.len() == k && (found[k - 1].0 as f64) < ring as f64 * self.cell_size {
+                break;
+            }
+        }
+
+        found.into_iter().map(|(_, city)| city).collect()
+    }
+
+    fn cell_of(&self, point: &Point) -> (usize, usize) {
+        let column = ((point.x - self.min_x) / self.cell_size) as usize;
+        let row = ((point.y - self.min_y) / self.cell_size) as usize;
+        (column.min(self.columns - 1), row.min(self.rows - 1))
+    }
+
+    /// Cells exactly `ring` steps from (`column`, `row`) in either direction, within the grid
+    fn ring(&self, column: usize, row: usize, ring: usize) -> Vec<(usize, usize)> {
+        let columns = column.saturating_sub(ring)..=(column + ring).min(self.columns - 1);
+        let rows = row.saturating_sub(ring)..=(row + ring).min(self.rows - 1);
+        rows.flat_map(|r| columns.clone().map(move |c| (c, r)))
+            .filter(|&(c, r)| c.abs_diff(column).max(r.abs_diff(row)) == ring)
+            .collect()
+    }
+}
+
+/// Neighbour lists like `grid::k_nearest` straight from the coordinates, through a
+/// `SpatialGrid`, without building the n×n Euclidean grid first
+pub fn k_nearest_points(points: &[Point], k: usize) -> Vec<Vec<usize>> {
+    let index = build_spatial_grid(points, default_cell_size(points));
+    (0..points.len()).map(|i| index.k_nearest(i, k)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::{calculate_edge_grid, k_nearest};
+    use crate::point::generate_points_with;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn index_finds_the_same_neighbours_as_a_full_scan() {
+        for seed in 0..5 {
+            let mut points = generate_points_with(150, 1000, seed % 2 == 0, &mut StdRng::seed_from_u64(seed)).unwrap();
+            // a tight cluster far from the rest, so most cells are empty
+            points.extend((0..5).map(|i| Point { x: 5000.0 + i as f64, y: 5000.0, label: None }));
+            let grid = calculate_edge_grid(points.clone()).unwrap();
+
+            assert_eq!(k_nearest_points(&points, 6), k_nearest(&grid, 6), "seed {seed}");
+            let index = build_spatial_grid(&points, 0.0);
+            for (i, nearest) in k_nearest(&grid, 1).iter().enumerate() {
+                assert_eq!(index.nearest(i), nearest.first().copied(), "seed {seed}, city {i}");
+            }
+        }
+    }
+
+    #[test]
+    fn degenerate_layouts_still_answer() {
+        let stacked = vec![Point { x: 3.0, y: 3.0, label: None }; 4];
+        assert_eq!(build_spatial_grid(&stacked, 0.0).k_nearest(2, 3), vec![0, 1, 3]);
+
+        let single = [Point { x: 0.0, y: 0.0, label: None }];
+        assert_eq!(build_spatial_grid(&single, 1.0).nearest(0), None);
+    }
+}
@@ -0,0 +1,348 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use crate::distance::Distance;
+use crate::error::TspError;
+use crate::grid::validate_grid;
+
+/// Checks `tour` visits every city of an n-city instance exactly once
+pub fn validate_tour(tour: &[usize], n: usize) -> Result<(), TspError> {
+    if tour.len() != n {
+        return Err(TspError::InvalidTour(format!("expected {n} cities, got {}", tour.len())));
+    }
+    let mut seen = vec![false; n];
+    for &city in tour {
+        if city >= n {
+            return Err(TspError::InvalidTour(format!("city {city} is out of range")));
+        }
+        if seen[city] {
+            return Err(TspError::InvalidTour(format!("city {city} is visited more than once")));
+        }
+        seen[city] = true;
+    }
+
+    Ok(())
+}
+
+/// Length of the closed tour, including the return to its first city
+pub fn tour_length(grid: &[Vec<f32>], tour: &[usize]) -> Result<f32, TspError> {
+    validate_grid(grid)?;
+    validate_tour(tour, grid.len())?;
+
+    let n = tour.len();
+    let mut total_distance = grid[tour[n-1]][tour[0]];
+    for i in 0..n-1 {
+        total_distance += grid[tour[i]][tour[i+1]];
+    }
+
+    Ok(total_distance)
+}
+
+/// Fractional gap `(length - bound) / bound` of a tour over a lower bound or known optimum, e.g.
+/// 0.1 for a tour 10% too long. Never negative, as a length just under the optimum is float
+/// rounding. A zero bound (a single city, or cities all in one place) gives 0.0.
+pub fn optimality_gap(length: f32, bound: f32) -> f32 {
+    if bound > 0.0 { ((length - bound) / bound).max(0.0) } else { 0.0 }
+}
+
+/// Rotates the closed tour so it starts at `depot`, keeping the cyclic order.
+/// A tour that doesn't contain `depot` is returned unchanged.
+pub fn rotate_to(tour: &[usize], depot: usize) -> Vec<usize> {
+    let mut rotated = tour.to_vec();
+    if let Some(position) = tour.iter().position(|x| *x == depot) {
+        rotated.rotate_left(position);
+    }
+
+    rotated
+}
+
+/// The one way of writing a closed tour shared by all its rotations and its reversal: starting
+/// from its smallest city, heading towards the smaller of that city's two neighbours
+pub fn canonical_tour(tour: &[usize]) -> Vec<usize> {
+    let Some(&smallest) = tour.iter().min() else {
+        return Vec::new();
+    };
+    let mut canonical = rotate_to(tour, smallest);
+    if canonical.len() > 2 && canonical[canonical.len()-1] < canonical[1] {
+        canonical[1..].reverse();
+    }
+
+    canonical
+}
+
+/// Hash of `canonical_tour`, so a tour, its rotations and its reversal all collide, e.g. for
+/// deduplicating results in a `HashSet<u64>`
+pub fn tour_hash(tour: &[usize]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    canonical_tour(tour).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Successor map of the closed tour: `succ[i]` is the city visited right after city `i`.
+/// `tour` must be a permutation of `0..tour.len()`.
+pub fn tour_to_successors(tour: &[usize]) -> Vec<usize> {
+    let n = tour.len();
+    let mut succ = vec![0; n];
+    for i in 0..n {
+        succ[tour[i]] = tour[(i + 1) % n];
+    }
+
+    succ
+}
+
+/// The closed tour as an n×n 0/1 matrix with `matrix[i][j] == 1` when the tour goes straight from
+/// city i to city j: a permutation matrix with a single 1 in every row and column, and none on the
+/// diagonal once there are two cities. `tour` must be a permutation of `0..tour.len()`.
+pub fn tour_to_matrix(tour: &[usize]) -> Vec<Vec<u8>> {
+    let n = tour.len();
+    let mut matrix = vec![vec![0; n]; n];
+    for (city, next) in tour_to_successors(tour).into_iter().enumerate() {
+        matrix[city][next] = 1;
+    }
+
+    matrix
+}
+
+/// Walks a successor map back into a tour, starting from `start`
+pub fn successors_to_tour(succ: &[usize], start: usize) -> Vec<usize> {
+    let mut tour = Vec::with_capacity(succ.len());
+    let mut city = start;
+    for _ in 0..succ.len() {
+        tour.push(city);
+        city = succ[city];
+    }
+
+    tour
+}
+
+/// Uniformly random tour of `n` cities that starts at city 0, like every solver's tours
+pub fn random_tour<R: Rng>(n: usize, rng: &mut R) -> Vec<usize> {
+    if n == 0 {
+        return Vec::new();
+    }
+
+    let mut tour: Vec<usize> = (0..n).collect();
+    tour[1..].shuffle(rng);
+
+    tour
+}
+
+/// Lengths of `samples` random tours, reproducible from `seed`: a baseline showing how much better
+/// than chance a solver's tour is
+pub fn sample_tour_lengths(grid: &[Vec<f32>], samples: usize, seed: u64) -> Result<Vec<f32>, TspError> {
+    validate_grid(grid)?;
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    (0..samples).map(|_| tour_length(grid, &random_tour(grid.len(), &mut rng))).collect()
+}
+
+/// Cuts the closed tour into consecutive day-routes that each start at `depot` and return to it
+/// within `daily_budget`, keeping the tour's order. A day takes cities greedily until the next
+/// one plus the drive home would go over budget. `Infeasible` if a city is too far from the
+/// depot to visit even on a day of its own.
+pub fn split_into_days(tour: &[usize], grid: &[Vec<f32>], depot: usize, daily_budget: f32) -> Result<Vec<Vec<usize>>, TspError> {
+    validate_grid(grid)?;
+    validate_tour(tour, grid.len())?;
+    if depot >= grid.len() {
+        return Err(TspError::InvalidTour(format!("depot {depot} is not one of the {} cities", grid.len())));
+    }
+
+    let mut days = Vec::new();
+    let mut day = vec![depot];
+    let mut driven = 0.0;
+    for &city in rotate_to(tour, depot).iter().skip(1) {
+        if grid[depot][city] + grid[city][depot] > daily_budget {
+            return Err(TspError::Infeasible);
+        }
+        let last = day[day.len()-1];
+        if driven + grid[last][city] + grid[city][depot] > daily_budget {
+            days.push(std::mem::replace(&mut day, vec![depot]));
+            driven = 0.0;
+        }
+        driven += grid[day[day.len()-1]][city];
+        day.push(city);
+    }
+    if day.len() > 1 {
+        days.push(day);
+    }
+
+    Ok(days)
+}
+
+/// Undirected edge between two cities, stored as (smaller city, larger city)
+pub type Edge = (usize, usize);
+
+/// Edges of the closed tour; direction doesn't matter
+pub fn tour_edges(tour: &[usize]) -> Vec<Edge> {
+    let n = tour.len();
+    (0..n)
+        .filter(|&i| n > 2 || i + 1 < n)
+        .map(|i| {
+            let (a, b) = (tour[i], tour[(i + 1) % n]);
+            (a.min(b), a.max(b))
+        })
+        .collect()
+}
+
+/// The longest leg of the closed tour, closing edge back to the start included, as (from, to,
+/// length); the first one in tour order on a tie. `tour` must not be empty.
+pub fn bottleneck_edge<D: Distance>(tour: &[usize], grid: &[Vec<D>]) -> (usize, usize, D) {
+    let n = tour.len();
+    let (first, second) = (tour[0], tour[1 % n]);
+    let mut longest = (first, second, grid[first][second]);
+    for i in 1..n {
+        let (from, to) = (tour[i], tour[(i + 1) % n]);
+        if grid[from][to] > longest.2 {
+            longest = (from, to, grid[from][to]);
+        }
+    }
+
+    longest
+}
+
+/// Percentage of `a`'s edges that `b` also uses, direction ignored: 100 for the same closed tour
+/// however it's rotated or mirrored
+pub fn edge_overlap_percent(a: &[usize], b: &[usize]) -> f32 {
+    let (edges_a, edges_b) = (tour_edges(a), tour_edges(b));
+    if edges_a.is_empty() {
+        return 100.0;
+    }
+    let shared = edges_a.iter().filter(|e| edges_b.contains(e)).count();
+
+    shared as f32 / edges_a.len() as f32 * 100.0
+}
+
+/// Edges found in only one of the two tours: (only in `a`, only in `b`)
+pub fn edge_difference(a: &[usize], b: &[usize]) -> (Vec<Edge>, Vec<Edge>) {
+    let (edges_a, edges_b) = (tour_edges(a), tour_edges(b));
+    let only_a = edges_a.iter().filter(|e| !edges_b.contains(e)).cloned().collect();
+    let only_b = edges_b.iter().filter(|e| !edges_a.contains(e)).cloned().collect();
+
+    (only_a, only_b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::brute_force::brute_force;
+    use crate::grid::calculate_edge_grid;
+    use crate::point::{generate_points_with, Point};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn rotating_keeps_the_cyclic_order() {
+        assert_eq!(rotate_to(&[0, 2, 3, 1], 3), vec![3, 1, 0, 2]);
+        assert_eq!(rotate_to(&[0, 2, 3, 1], 0), vec![0, 2, 3, 1]);
+        assert_eq!(rotate_to(&[0, 2, 3, 1], 9), vec![0, 2, 3, 1]);
+    }
+
+    #[test]
+    fn successors_round_trip() {
+        let tour = vec![0, 4, 2, 1, 3];
+        let succ = tour_to_successors(&tour);
+        assert_eq!(succ, vec![4, 3, 1, 0, 2]);
+        assert_eq!(successors_to_tour(&succ, 0), tour);
+        // any start walks the same cycle
+        assert_eq!(successors_to_tour(&succ, 2), rotate_to(&tour, 2));
+    }
+
+    #[test]
+    fn random_tours_are_permutations_from_city_0() {
+        for seed in 0..200 {
+            let tour = random_tour(9, &mut StdRng::seed_from_u64(seed));
+            validate_tour(&tour, 9).unwrap();
+            assert_eq!(tour[0], 0, "seed {seed}");
+        }
+        assert_ne!(random_tour(9, &mut StdRng::seed_from_u64(1)), random_tour(9, &mut StdRng::seed_from_u64(2)));
+        assert_eq!(random_tour(9, &mut StdRng::seed_from_u64(1)), random_tour(9, &mut StdRng::seed_from_u64(1)));
+        assert!(random_tour(0, &mut StdRng::seed_from_u64(1)).is_empty());
+    }
+
+    fn seeded_grid(n: usize, seed: u64) -> Vec<Vec<f32>> {
+        calculate_edge_grid(generate_points_with(n, 100, false, &mut StdRng::seed_from_u64(seed)).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn sampled_tours_are_never_shorter_than_the_optimum() {
+        let grid = seeded_grid(7, 5);
+        let (_, optimum) = brute_force(&grid).unwrap();
+
+        let lengths = sample_tour_lengths(&grid, 500, 9).unwrap();
+        assert_eq!(lengths.len(), 500);
+        let shortest = lengths.iter().copied().fold(f32::INFINITY, f32::min);
+        assert!(shortest >= optimum * (1.0 - 1e-5));
+        assert!(lengths.iter().any(|&length| length > shortest));
+        assert_eq!(sample_tour_lengths(&grid, 500, 9).unwrap(), lengths);
+    }
+
+    #[test]
+    fn rotations_and_reversals_hash_alike() {
+        let tour = [2, 0, 4, 1, 3];
+        assert_eq!(canonical_tour(&tour), vec![0, 2, 3, 1, 4]);
+        for start in 0..5 {
+            let rotated = rotate_to(&tour, start);
+            let mut reversed = rotated.clone();
+            reversed.reverse();
+            assert_eq!(tour_hash(&rotated), tour_hash(&tour));
+            assert_eq!(tour_hash(&reversed), tour_hash(&tour));
+        }
+        assert_ne!(tour_hash(&[0, 1, 2, 3, 4]), tour_hash(&tour));
+    }
+
+    #[test]
+    fn day_routes_fill_up_to_the_budget() {
+        let line = (0..4).map(|x| Point { x: x as f64, y: 0.0, label: None }).collect();
+        let grid = calculate_edge_grid(line).unwrap();
+        let tour = [0, 3, 1, 2];
+
+        assert_eq!(split_into_days(&tour, &grid, 0, 8.0).unwrap(), vec![vec![0, 3, 1, 2]]);
+        // 0 -> 3 -> 1 and home is 6; going on to 2 first would make it 8
+        assert_eq!(split_into_days(&tour, &grid, 0, 6.0).unwrap(), vec![vec![0, 3, 1], vec![0, 2]]);
+        assert!(matches!(split_into_days(&tour, &grid, 0, 5.0), Err(TspError::Infeasible)));
+        assert_eq!(split_into_days(&tour, &grid, 2, 4.0).unwrap(), vec![vec![2, 0], vec![2, 3, 1]]);
+    }
+
+    #[test]
+    fn gap_is_the_fraction_over_the_bound() {
+        assert_eq!(optimality_gap(110.0, 100.0), 0.1);
+        assert_eq!(optimality_gap(100.0, 100.0), 0.0);
+        assert_eq!(optimality_gap(99.99, 100.0), 0.0);
+        assert_eq!(optimality_gap(5.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn tour_matrix_is_a_permutation_matrix_with_an_empty_diagonal() {
+        for seed in 0..10 {
+            let tour = random_tour(7, &mut StdRng::seed_from_u64(seed));
+            let matrix = tour_to_matrix(&tour);
+            for i in 0..7 {
+                assert_eq!(matrix[i].iter().map(|&x| x as usize).sum::<usize>(), 1);
+                assert_eq!(matrix.iter().map(|row| row[i] as usize).sum::<usize>(), 1);
+                assert_eq!(matrix[i][i], 0);
+            }
+            assert_eq!(matrix[tour[6]][tour[0]], 1);
+        }
+        assert_eq!(tour_to_matrix(&[0]), vec![vec![1]]);
+    }
+
+    #[test]
+    fn bottleneck_is_the_longest_leg_closing_edge_included() {
+        let line = |xs: &[f32]| -> Vec<Vec<f32>> {
+            xs.iter().map(|a| xs.iter().map(|b| (a - b).abs()).collect()).collect()
+        };
+        // 0 -> 1 -> 2 runs 1 then 4, and closing 2 -> 0 is the 5
+        let grid = line(&[0.0, 1.0, 5.0]);
+        assert_eq!(bottleneck_edge(&[0, 1, 2], &grid), (2, 0, 5.0));
+        assert_eq!(bottleneck_edge(&[1, 2, 0], &grid), (2, 0, 5.0));
+
+        // legs of 1, 2, 1, 2: the first 2 in tour order wins
+        let grid = line(&[0.0, 1.0, 3.0, 2.0]);
+        assert_eq!(bottleneck_edge(&[0, 1, 2, 3], &grid), (1, 2, 2.0));
+        assert_eq!(bottleneck_edge(&[2, 3, 0, 1], &grid), (3, 0, 2.0));
+
+        assert_eq!(bottleneck_edge(&[4], &line(&[0.0; 5])), (4, 4, 0.0));
+    }
+}